@@ -0,0 +1,9 @@
+#![no_main]
+
+use kf_feeder::transcode::Source;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let url = "https://www.jvcmusic.co.jp/-/News/A025287.json";
+    kf_feeder_fuzz::transcode(Source::JvcmusicCoJp, url, data);
+});
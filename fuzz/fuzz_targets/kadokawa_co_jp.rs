@@ -0,0 +1,9 @@
+#![no_main]
+
+use kf_feeder::transcode::Source;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let url = "https://www.kadokawa.co.jp/json.jsp?id=342&category=comic";
+    kf_feeder_fuzz::transcode(Source::KadokawaCoJp, url, data);
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use kf_feeder::transcode::Source;
+use libfuzzer_sys::fuzz_target;
+use reqwest::Url;
+
+fuzz_target!(|data: &[u8]| {
+    // The router parses request paths without the leading slash into upstream URLs like this.
+    if let Ok(Ok(url)) = std::str::from_utf8(data).map(str::parse::<Url>) {
+        Source::from_url(&url);
+    }
+});
@@ -0,0 +1,50 @@
+//! Fuzz targets of the transcoders and the route matching.
+//!
+//! Run a target with `cargo +nightly fuzz run <target>` from the repository root. The upstream
+//! fixtures make a good starting corpus for the transcoders:
+//!
+//! ```sh
+//! cargo +nightly fuzz run kemono_friends_sega_jp \
+//!     fuzz/corpus/kemono_friends_sega_jp tests/fixtures/kemono-friends-sega-jp
+//! ```
+
+use std::cell::RefCell;
+
+use bytes::Bytes;
+use futures::stream;
+use kf_feeder::{feed::Feed, transcode::Source};
+use tokio::runtime::{Builder, Runtime};
+
+thread_local! {
+    static RUNTIME: RefCell<Runtime> = RefCell::new(
+        Builder::new().basic_scheduler().build().unwrap(),
+    );
+}
+
+/// Feeds `data` to the transcoder of `source` and checks that any output it accepts is XML.
+///
+/// The first byte of `data` is the size of the chunks the rest is fed in, so that the fuzzer
+/// also explores the chunk boundaries.
+pub fn transcode(source: Source, url: &str, data: &[u8]) {
+    let (chunk_size, data) = match data.split_first() {
+        Some((&size, data)) => (usize::from(size).max(1), data),
+        None => return,
+    };
+    let chunks = data
+        .chunks(chunk_size)
+        .map(|chunk| Ok::<_, reqwest::Error>(Bytes::copy_from_slice(chunk)))
+        .collect::<Vec<_>>();
+
+    let url = url.parse().unwrap();
+    let result = RUNTIME.with(|runtime| {
+        let task = source.transcode_to_vec(url, stream::iter(chunks));
+        runtime.borrow_mut().block_on(task)
+    });
+
+    // Malformed upstream documents may be rejected, but they must not produce malformed feeds.
+    if let Ok(atom) = result {
+        if let Err(e) = Feed::parse(&atom) {
+            panic!("{}\n{}", e, String::from_utf8_lossy(&atom));
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Static generation of the polled feeds for mirrors that can only serve files.
+
+#[cfg(test)]
+mod tests;
+
+use std::{fmt::Write as _, fs, path::Path, sync::Arc};
+
+use bytes::Bytes;
+use hyper::{Method, StatusCode};
+use reqwest::{Request as Reqwest, Url};
+use xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+
+use crate::{
+    config,
+    feed::{self, Feed},
+    router::State,
+    transcode::Source,
+    util,
+};
+
+/// Fetches every configured feed once and writes the files of each, plus `index.html` and
+/// `feeds.opml`, into `out`.
+pub async fn run(state: &Arc<State>, out: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(out)?;
+
+    let mut feeds = Vec::new();
+    let mut failed = 0;
+    for config in &state.config.feeds {
+        let name = config.name();
+        let (atom, feed) = match fetch(state, &config.url).await {
+            Ok(feed) => feed,
+            Err(e) => {
+                tracing::error!(url = %config.url, error = %e, "failed to build the feed");
+                failed += 1;
+                // List the feed built last time, if any.
+                if let Ok(atom) = fs::read(out.join(name.clone() + ".atom")) {
+                    if let Ok(feed) = Feed::parse(&atom) {
+                        feeds.push((config, feed));
+                    }
+                }
+                continue;
+            }
+        };
+
+        let json_feed_url = file_url(state, &name, "json");
+        let json_feed = feed.to_json_feed(json_feed_url.as_deref());
+        write(out, &name, "atom", &atom)?;
+        write(out, &name, "rss", &feed.to_rss()?)?;
+        write(out, &name, "json", &json::to_vec(&json_feed)?)?;
+        feeds.push((config, feed));
+    }
+
+    util::write_atomic(&out.join("index.html"), index(&feeds).as_bytes())?;
+    util::write_atomic(&out.join("feeds.opml"), &opml(state, &feeds)?)?;
+
+    if failed > 0 {
+        anyhow::bail!(
+            "failed to fetch {} of {} feeds",
+            failed,
+            state.config.feeds.len()
+        );
+    }
+    Ok(())
+}
+
+/// Fetches the feed of `url`, returning the Atom document and its model.
+async fn fetch(state: &State, url: &Url) -> anyhow::Result<(Bytes, Feed)> {
+    let source = Source::from_url(url).unwrap();
+    let resw = state.upstreams[&source]
+        .execute(Reqwest::new(Method::GET, url.clone()))
+        .await?;
+    if resw.status() != StatusCode::OK {
+        anyhow::bail!("upstream responded with {}", resw.status());
+    }
+
+    let atom = source
+        .transcode_to_vec(url.clone(), resw.bytes_stream())
+        .await?;
+    let atom = Bytes::from(atom);
+    let mut feed = state.produce(source, url, &atom)?;
+    let atom = state.history.apply(url, &mut feed, atom).await?;
+    Ok((atom, feed))
+}
+
+fn write(out: &Path, name: &str, extension: &str, contents: &[u8]) -> std::io::Result<()> {
+    util::write_atomic(&out.join(format!("{}.{}", name, extension)), contents)
+}
+
+/// Returns the URL of a built file, absolute if `public-url` is configured.
+fn file_url(state: &State, name: &str, extension: &str) -> Option<String> {
+    let file = format!("{}.{}", name, extension);
+    let url = state.config.public_url.as_ref()?.join(&file).ok()?;
+    Some(url.into())
+}
+
+fn index(feeds: &[(&config::Feed, Feed)]) -> String {
+    let mut html = String::from(concat!(
+        "<!DOCTYPE html>\n",
+        "<html lang=\"ja\">\n",
+        "<meta charset=\"utf-8\">\n",
+        "<title>kf-feeder</title>\n",
+    ));
+    for (config, feed) in feeds {
+        writeln!(
+            html,
+            r#"<link rel="alternate" type="application/atom+xml" title="{}" href="{}.atom">"#,
+            util::escape_html(&feed.title),
+            util::escape_html(&config.name()),
+        )
+        .unwrap();
+    }
+    html.push_str("<h1>Feeds</h1>\n<ul>\n");
+    for (config, feed) in feeds {
+        let name = util::escape_html(&config.name());
+        writeln!(
+            html,
+            r#"<li><a href="{0}.atom">{1}</a> (<a href="{0}.rss">RSS</a>, <a href="{0}.json">JSON Feed</a>)"#,
+            name,
+            util::escape_html(&feed.title),
+        )
+        .unwrap();
+    }
+    html.push_str("</ul>\n<p><a href=\"feeds.opml\">OPML</a>\n");
+    html
+}
+
+fn opml(state: &State, feeds: &[(&config::Feed, Feed)]) -> feed::Result<Vec<u8>> {
+    let mut writer = xml::Writer::new(Vec::new());
+    writer.write_event(Event::Decl(BytesDecl::new(b"1.0", Some(b"utf-8"), None)))?;
+    writer.write_event(Event::Start(BytesStart::borrowed(
+        br#"opml version="2.0""#,
+        4,
+    )))?;
+
+    writer.write_event(Event::Start(BytesStart::borrowed_name(b"head")))?;
+    writer.write_event(Event::Start(BytesStart::borrowed_name(b"title")))?;
+    writer.write_event(Event::Text(BytesText::from_plain_str("kf-feeder")))?;
+    writer.write_event(Event::End(BytesEnd::borrowed(b"title")))?;
+    writer.write_event(Event::End(BytesEnd::borrowed(b"head")))?;
+
+    writer.write_event(Event::Start(BytesStart::borrowed_name(b"body")))?;
+    for (config, feed) in feeds {
+        let name = config.name();
+        let xml_url = file_url(state, &name, "atom").unwrap_or_else(|| name + ".atom");
+        let mut outline = BytesStart::borrowed_name(b"outline");
+        outline.push_attribute(("type", "rss"));
+        outline.push_attribute(("text", &*feed.title));
+        outline.push_attribute(("title", &*feed.title));
+        outline.push_attribute(("xmlUrl", &*xml_url));
+        if let Some(ref link) = feed.link {
+            outline.push_attribute(("htmlUrl", &**link));
+        }
+        writer.write_event(Event::Empty(outline))?;
+    }
+    writer.write_event(Event::End(BytesEnd::borrowed(b"body")))?;
+
+    writer.write_event(Event::End(BytesEnd::borrowed(b"opml")))?;
+    Ok(writer.into_inner())
+}
@@ -0,0 +1,70 @@
+use super::{index, opml};
+use crate::{config::Config, feed::Feed, router::State};
+
+fn state() -> State {
+    let config = r#"
+        public-url = "https://feeds.example.com/"
+
+        [[feeds]]
+        url = "https://kemono-friends.sega.jp/news/articles.json"
+        name = "kemono-friends"
+
+        [[feeds]]
+        url = "https://www.jvcmusic.co.jp/-/News/A025287.json"
+        name = "biscuits"
+    "#;
+    let config: Config = toml::from_str(config).unwrap();
+    State::new(config, None).unwrap()
+}
+
+fn feeds() -> Vec<Feed> {
+    vec![
+        Feed {
+            title: "けものフレンズ３".to_owned(),
+            link: Some("https://kemono-friends.sega.jp/".to_owned()),
+            ..Feed::default()
+        },
+        Feed {
+            title: "ビスケッツ & PPP <NEWS>".to_owned(),
+            ..Feed::default()
+        },
+    ]
+}
+
+#[test]
+fn writes_index() {
+    let state = state();
+    let feeds = state.config.feeds.iter().zip(feeds()).collect::<Vec<_>>();
+    let expected = concat!(
+        "<!DOCTYPE html>\n",
+        "<html lang=\"ja\">\n",
+        "<meta charset=\"utf-8\">\n",
+        "<title>kf-feeder</title>\n",
+        "<link rel=\"alternate\" type=\"application/atom+xml\" title=\"けものフレンズ３\" href=\"kemono-friends.atom\">\n",
+        "<link rel=\"alternate\" type=\"application/atom+xml\" title=\"ビスケッツ &amp; PPP &lt;NEWS&gt;\" href=\"biscuits.atom\">\n",
+        "<h1>Feeds</h1>\n",
+        "<ul>\n",
+        "<li><a href=\"kemono-friends.atom\">けものフレンズ３</a> (<a href=\"kemono-friends.rss\">RSS</a>, <a href=\"kemono-friends.json\">JSON Feed</a>)\n",
+        "<li><a href=\"biscuits.atom\">ビスケッツ &amp; PPP &lt;NEWS&gt;</a> (<a href=\"biscuits.rss\">RSS</a>, <a href=\"biscuits.json\">JSON Feed</a>)\n",
+        "</ul>\n",
+        "<p><a href=\"feeds.opml\">OPML</a>\n",
+    );
+    assert_eq!(index(&feeds), expected);
+}
+
+#[test]
+fn writes_opml() {
+    let state = state();
+    let feeds = state.config.feeds.iter().zip(feeds()).collect::<Vec<_>>();
+    let expected = concat!(
+        r#"<?xml version="1.0" encoding="utf-8"?><opml version="2.0">"#,
+        "<head><title>kf-feeder</title></head><body>",
+        r#"<outline type="rss" text="けものフレンズ３" title="けものフレンズ３" xmlUrl="https://feeds.example.com/kemono-friends.atom" htmlUrl="https://kemono-friends.sega.jp/"/>"#,
+        r#"<outline type="rss" text="ビスケッツ &amp; PPP &lt;NEWS&gt;" title="ビスケッツ &amp; PPP &lt;NEWS&gt;" xmlUrl="https://feeds.example.com/biscuits.atom"/>"#,
+        "</body></opml>",
+    );
+    assert_eq!(
+        String::from_utf8(opml(&state, &feeds).unwrap()).unwrap(),
+        expected
+    );
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use hyper::{
+    header::{HeaderMap, HeaderValue, AGE, CONTENT_LENGTH, ETAG},
+    Body, Response, StatusCode,
+};
+
+use crate::feed::Feed;
+
+/// Maximum number of feeds in the cache.
+const CAPACITY: usize = 1024;
+
+/// The last successfully transcoded feed for each upstream URL, each with how long it may be
+/// served.
+#[derive(Clone, Default)]
+pub struct Cache(Arc<Mutex<Entries>>);
+
+type Entries = HashMap<String, (Arc<Entry>, Duration)>;
+
+pub struct Entry {
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    pub feed: Feed,
+    pub time: SystemTime,
+}
+
+impl Cache {
+    pub fn get(&self, url: &str) -> Option<Arc<Entry>> {
+        let entries = self.0.lock().unwrap();
+        entries.get(url).map(|(entry, _)| entry.clone())
+    }
+
+    /// Inserts `entry`, which may be served until it is older than `lifetime`.
+    ///
+    /// Expired entries are evicted, and so is the oldest entry when the cache is full.
+    pub fn insert(&self, url: String, entry: Entry, lifetime: Duration) {
+        let mut entries = self.0.lock().unwrap();
+        entries.retain(|_, (entry, lifetime)| entry.age() <= *lifetime);
+        if entries.len() >= CAPACITY && !entries.contains_key(&url) {
+            let oldest = entries
+                .iter()
+                .max_by_key(|(_, (entry, _))| entry.age())
+                .map(|(url, _)| url.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(url, (Arc::new(entry), lifetime));
+    }
+
+    /// Returns the URL and the age of every entry.
+    pub fn ages(&self) -> Vec<(String, Duration)> {
+        let entries = self.0.lock().unwrap();
+        entries
+            .iter()
+            .map(|(url, (entry, _))| (url.clone(), entry.age()))
+            .collect()
+    }
+}
+
+impl Entry {
+    pub fn new(headers: HeaderMap, body: Bytes, feed: Feed) -> Self {
+        Entry {
+            headers,
+            body,
+            feed,
+            time: SystemTime::now(),
+        }
+    }
+
+    pub fn age(&self) -> Duration {
+        self.time.elapsed().unwrap_or_default()
+    }
+
+    pub fn response(&self, head: bool, if_none_match: Option<&HeaderValue>) -> Response<Body> {
+        let mut res = Response::builder();
+        let headers = res.headers_mut().unwrap();
+        headers.clone_from(&self.headers);
+        headers.insert(AGE, self.age().as_secs().into());
+
+        let etag = self.headers.get(ETAG);
+        if let (Some(etag), Some(if_none_match)) = (etag, if_none_match) {
+            if matches(etag, if_none_match) {
+                return res
+                    .status(StatusCode::NOT_MODIFIED)
+                    .body(Body::default())
+                    .unwrap();
+            }
+        }
+
+        headers.insert(CONTENT_LENGTH, self.body.len().into());
+        let body = if head {
+            Body::default()
+        } else {
+            Body::from(self.body.clone())
+        };
+        res.body(body).unwrap()
+    }
+}
+
+/// Checks `If-None-Match` against `etag` using the weak comparison.
+fn matches(etag: &HeaderValue, if_none_match: &HeaderValue) -> bool {
+    let weak = |tag: &[u8]| tag.strip_prefix(b"W/").unwrap_or(tag).to_vec();
+    let etag = weak(etag.as_bytes());
+    if_none_match
+        .as_bytes()
+        .split(|&b| b == b',')
+        .map(|tag| tag.trim_ascii())
+        .any(|tag| tag == b"*" || weak(tag) == etag)
+}
@@ -0,0 +1,30 @@
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use hyper::header::HeaderMap;
+
+use super::{Cache, Entry, CAPACITY};
+use crate::feed::Feed;
+
+fn entry(age_secs: u64) -> Entry {
+    let mut entry = Entry::new(HeaderMap::new(), Bytes::new(), Feed::default());
+    entry.time = SystemTime::now() - Duration::from_secs(age_secs);
+    entry
+}
+
+#[test]
+fn evicts_expired_and_oldest_entries() {
+    let lifetime = Duration::from_secs(30);
+    let cache = Cache::default();
+    cache.insert("expired".to_owned(), entry(60), lifetime);
+    cache.insert("oldest".to_owned(), entry(10), lifetime);
+    assert!(cache.get("expired").is_none());
+    assert!(cache.get("oldest").is_some());
+
+    for i in 0..CAPACITY {
+        cache.insert(i.to_string(), entry(0), lifetime);
+    }
+    assert!(cache.get("oldest").is_none());
+    assert!(cache.get("0").is_some());
+    assert_eq!(cache.ages().len(), CAPACITY);
+}
@@ -0,0 +1,180 @@
+use std::{
+    io::{self, Write},
+    mem,
+};
+
+use bytes::Bytes;
+use flate2::{
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use futures::{stream, StreamExt};
+use hyper::{
+    header::{HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, VARY},
+    Body, Response, StatusCode,
+};
+
+use crate::util;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+    Deflate,
+}
+
+enum Encoder {
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(ZlibEncoder<Vec<u8>>),
+}
+
+impl Encoding {
+    /// The supported encodings, in order of preference.
+    const ALL: [Encoding; 3] = [Encoding::Brotli, Encoding::Gzip, Encoding::Deflate];
+
+    /// Chooses the preferred encoding acceptable according to an `Accept-Encoding` header.
+    pub fn negotiate(accept_encoding: Option<&HeaderValue>) -> Option<Self> {
+        let accept_encoding = accept_encoding?.to_str().ok()?;
+
+        let mut qvalues = [None; 3];
+        let mut wildcard = None;
+        for item in accept_encoding.split(',') {
+            let mut params = item.split(';');
+            let coding = params.next().unwrap().trim();
+            let q = params
+                .find_map(|p| {
+                    let p = p.trim();
+                    let q = p.strip_prefix("q=").or_else(|| p.strip_prefix("Q="))?;
+                    q.trim().parse::<f32>().ok()
+                })
+                .unwrap_or(1.);
+
+            if coding == "*" {
+                wildcard = Some(q);
+            } else if let Some(i) = Self::ALL.iter().position(|e| e.matches(coding)) {
+                qvalues[i] = Some(q);
+            }
+        }
+
+        let mut best: Option<(Encoding, f32)> = None;
+        for (&encoding, q) in Self::ALL.iter().zip(&qvalues) {
+            let q = q.or(wildcard).unwrap_or(0.);
+            if q > 0. && !matches!(best, Some((_, best)) if q <= best) {
+                best = Some((encoding, q));
+            }
+        }
+        best.map(|(encoding, _)| encoding)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    fn matches(self, coding: &str) -> bool {
+        coding.eq_ignore_ascii_case(self.as_str())
+            || self == Encoding::Gzip && coding.eq_ignore_ascii_case("x-gzip")
+    }
+}
+
+/// Compresses a successful feed response with `encoding`, if any.
+///
+/// A `304 Not Modified` response only gets the headers of the response it stands for.
+pub fn apply(encoding: Option<Encoding>, res: &mut Response<Body>) {
+    let not_modified = match res.status() {
+        StatusCode::OK if res.headers().contains_key(CONTENT_TYPE) => false,
+        StatusCode::NOT_MODIFIED => true,
+        _ => return,
+    };
+
+    let headers = res.headers_mut();
+    headers.append(VARY, HeaderValue::from_static("Accept-Encoding"));
+
+    let encoding = if let Some(encoding) = encoding {
+        encoding
+    } else {
+        return;
+    };
+
+    // The compressed representation is not byte-for-byte identical to the uncompressed one.
+    if let Some(etag) = headers.get(ETAG) {
+        let weak = util::weak_etag(etag);
+        headers.insert(ETAG, weak);
+    }
+    if not_modified {
+        return;
+    }
+    headers.insert(
+        CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    headers.remove(CONTENT_LENGTH);
+
+    let body = mem::take(res.body_mut());
+    *res.body_mut() = encode(body, encoding);
+}
+
+fn encode(body: Body, encoding: Encoding) -> Body {
+    let encoder = match encoding {
+        Encoding::Brotli => {
+            let (buffer_size, quality, lgwin) = (4096, 5, 22);
+            let w = brotli::CompressorWriter::new(Vec::new(), buffer_size, quality, lgwin);
+            Encoder::Brotli(Box::new(w))
+        }
+        Encoding::Gzip => Encoder::Gzip(GzEncoder::new(Vec::new(), Compression::default())),
+        Encoding::Deflate => Encoder::Deflate(ZlibEncoder::new(Vec::new(), Compression::default())),
+    };
+
+    let stream = stream::unfold(Some((body, encoder)), |state| async move {
+        let (mut body, mut encoder) = state?;
+        while let Some(chunk) = body.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => return Some((Err(io::Error::other(e)), None)),
+            };
+            if let Err(e) = encoder.write_all(&chunk) {
+                return Some((Err(e), None));
+            }
+            let output = encoder.take();
+            if !output.is_empty() {
+                return Some((Ok(output), Some((body, encoder))));
+            }
+        }
+        Some((encoder.finish(), None))
+    });
+
+    Body::wrap_stream(stream)
+}
+
+impl Encoder {
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match *self {
+            Encoder::Brotli(ref mut w) => w.write_all(buf),
+            Encoder::Gzip(ref mut w) => w.write_all(buf),
+            Encoder::Deflate(ref mut w) => w.write_all(buf),
+        }
+    }
+
+    /// Takes the compressed output produced so far.
+    fn take(&mut self) -> Bytes {
+        let buf = match *self {
+            Encoder::Brotli(ref mut w) => w.get_mut(),
+            Encoder::Gzip(ref mut w) => w.get_mut(),
+            Encoder::Deflate(ref mut w) => w.get_mut(),
+        };
+        mem::take(buf).into()
+    }
+
+    fn finish(self) -> io::Result<Bytes> {
+        let buf = match self {
+            Encoder::Brotli(w) => w.into_inner(),
+            Encoder::Gzip(w) => w.finish()?,
+            Encoder::Deflate(w) => w.finish()?,
+        };
+        Ok(buf.into())
+    }
+}
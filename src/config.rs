@@ -0,0 +1,459 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use hyper::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, ACCEPT_LANGUAGE, IF_MODIFIED_SINCE,
+    IF_NONE_MATCH,
+};
+use reqwest::Url;
+use serde::{de, Deserialize, Deserializer};
+use url::Origin;
+
+use crate::{transcode, util};
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// `User-Agent` sent to the upstreams.
+    pub user_agent: String,
+    /// Client request headers that are forwarded to the upstreams, except `Accept-Encoding`.
+    #[serde(deserialize_with = "header_names")]
+    pub forward_headers: Vec<HeaderName>,
+    /// Directory where the polled feeds are persisted across restarts.
+    pub state_dir: Option<PathBuf>,
+    /// Public base URL of the feeder ending with a slash, e.g. `https://feeds.example.com/`.
+    pub public_url: Option<Url>,
+    /// Base URLs that upstream origins are fetched from instead of the origins themselves.
+    pub origins: Origins,
+    pub cors: Cors,
+    pub websub: WebSub,
+    /// Whether Prometheus metrics are served at `/metrics`.
+    pub metrics: bool,
+    /// Whether the status page of the sources is served at `/status`.
+    pub status: bool,
+    pub log: Log,
+    /// Seconds that a shutdown waits for the requests and background tasks in flight.
+    #[serde(deserialize_with = "seconds")]
+    pub shutdown_timeout: Duration,
+    pub sources: Sources,
+    /// Feeds polled in the background.
+    pub feeds: Vec<Feed>,
+    /// URLs notified of new entries of the polled feeds.
+    pub webhooks: Vec<Webhook>,
+}
+
+/// Mapping from upstream origins to the base URLs they are fetched from.
+#[derive(Clone, Debug, Default)]
+pub struct Origins(HashMap<Origin, Url>);
+
+/// Cross-origin resource sharing settings applied to feed responses.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Cors {
+    /// Origins allowed to read the feeds. `"*"` allows any origin.
+    pub allow_origins: Vec<String>,
+    /// Response headers exposed to the reader besides `X-Request-Id`, e.g. `ETag`.
+    #[serde(deserialize_with = "header_names")]
+    pub expose_headers: Vec<HeaderName>,
+    /// Request headers allowed in a preflighted request.
+    #[serde(deserialize_with = "header_names")]
+    pub allow_headers: Vec<HeaderName>,
+    /// Number of seconds a preflight response may be cached.
+    pub max_age: Option<u64>,
+}
+
+/// Settings of the logs written to the standard error.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Log {
+    /// Minimum level of the records, or a filter like `"warn,kf_feeder=debug"`.
+    pub level: String,
+    pub format: LogFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// A JSON object per line.
+    Json,
+}
+
+/// Settings of the WebSub hub for the polled feeds.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WebSub {
+    pub enabled: bool,
+    /// Lease of a subscription that does not request one, in seconds.
+    #[serde(deserialize_with = "seconds")]
+    pub default_lease: Duration,
+    /// Maximum lease of a subscription, in seconds.
+    #[serde(deserialize_with = "seconds")]
+    pub max_lease: Duration,
+    /// Timeout for requests to the subscribers' callbacks, in seconds.
+    #[serde(deserialize_with = "seconds")]
+    pub timeout: Duration,
+    /// Maximum number of subscriptions to all the feeds.
+    pub max_subscriptions: usize,
+    /// Maximum number of subscriptions to a single feed.
+    pub max_subscriptions_per_topic: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sources {
+    #[serde(rename = "kemono-friends-sega-jp")]
+    pub kemono_friends_sega_jp: Source,
+    #[serde(rename = "kadokawa-co-jp")]
+    pub kadokawa_co_jp: Source,
+    #[serde(rename = "jvcmusic-co-jp")]
+    pub jvcmusic_co_jp: Source,
+}
+
+/// Settings for fetching from an upstream site.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Source {
+    /// Timeout for connecting to the upstream, in seconds.
+    #[serde(deserialize_with = "seconds")]
+    pub connect_timeout: Duration,
+    /// Timeout for the whole upstream request including its body, in seconds.
+    #[serde(deserialize_with = "seconds")]
+    pub timeout: Duration,
+    /// Number of times a failed `GET` or `HEAD` request is retried.
+    pub retries: u32,
+    /// Base delay of the exponential backoff between retries, in seconds.
+    #[serde(deserialize_with = "seconds")]
+    pub retry_backoff: Duration,
+    /// Number of consecutive failures after which the circuit breaker opens.
+    pub failure_threshold: u32,
+    /// How long the circuit breaker stays open before a trial request, in seconds.
+    #[serde(deserialize_with = "seconds")]
+    pub open_duration: Duration,
+    /// How long the last good feed may be served while the upstream is failing, in seconds.
+    #[serde(deserialize_with = "seconds")]
+    pub stale_if_error: Duration,
+    /// Default interval for polling the feeds of this source in the background, in seconds.
+    #[serde(deserialize_with = "seconds")]
+    pub poll_interval: Duration,
+    /// `max-age` of the feed responses, in seconds.
+    #[serde(deserialize_with = "seconds")]
+    pub ttl: Duration,
+    /// Additional headers sent with every request to the upstream.
+    #[serde(deserialize_with = "header_map")]
+    pub headers: HeaderMap,
+    /// What to do with a produced feed that violates RFC 4287.
+    pub validate: Validate,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Feed {
+    /// The upstream URL, as in the path of a feed request.
+    pub url: Url,
+    /// Base name of the files of the feed written by `build`. Defaults to a slug of the URL.
+    pub name: Option<String>,
+    /// Polling interval in seconds. Defaults to the `poll-interval` of the source.
+    #[serde(default, deserialize_with = "optional_seconds")]
+    pub interval: Option<Duration>,
+}
+
+/// A URL notified of new entries of the polled feeds.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Webhook {
+    pub url: Url,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Key of the HMAC-SHA256 signature of the payload, sent in `X-Kf-Feeder-Signature`.
+    pub secret: Option<String>,
+    /// Upstream URLs of the feeds to notify of. Defaults to all the polled feeds.
+    #[serde(default)]
+    pub feeds: Vec<Url>,
+    /// Timeout for a request to the webhook, in seconds.
+    #[serde(default = "default_webhook_timeout", deserialize_with = "seconds")]
+    pub timeout: Duration,
+    /// Number of times a failed delivery is retried.
+    #[serde(default = "default_webhook_retries")]
+    pub retries: u32,
+    /// Base delay of the exponential backoff between retries, in seconds.
+    #[serde(
+        default = "default_webhook_retry_backoff",
+        deserialize_with = "seconds"
+    )]
+    pub retry_backoff: Duration,
+    /// Maximum delay between retries, also capping `Retry-After`, in seconds.
+    #[serde(default = "default_webhook_max_backoff", deserialize_with = "seconds")]
+    pub max_backoff: Duration,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookFormat {
+    /// A JSON object describing the feed and the entry.
+    #[default]
+    Json,
+    /// A Discord webhook message with an embed.
+    Discord,
+    /// A Slack incoming webhook message.
+    Slack,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Validate {
+    /// The feeds are not validated.
+    #[default]
+    Off,
+    /// The violations are logged and the feed is served anyway.
+    Log,
+    /// The violations are logged and the feed is neither served nor stored.
+    Reject,
+}
+
+impl Config {
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let s = fs::read_to_string(path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        let config: Config =
+            toml::from_str(&s).with_context(|| format!("failed to parse `{}`", path.display()))?;
+        if config.forward_headers.contains(&ACCEPT_ENCODING) {
+            anyhow::bail!("`forward-headers` may not contain `Accept-Encoding`");
+        }
+        if let Some(ref url) = config.public_url {
+            if !url.path().ends_with('/') {
+                anyhow::bail!("`public-url` must end with a slash");
+            }
+        } else if config.websub.enabled {
+            anyhow::bail!("`public-url` is required by the WebSub hub");
+        }
+        for feed in &config.feeds {
+            if transcode::Source::from_url(&feed.url).is_none() {
+                anyhow::bail!("no transcoder for the feed `{}`", feed.url);
+            }
+        }
+        let mut names = std::collections::HashSet::new();
+        for feed in &config.feeds {
+            let name = feed.name();
+            if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+                anyhow::bail!("invalid name `{}` of the feed `{}`", name, feed.url);
+            }
+            if !names.insert(name) {
+                anyhow::bail!("duplicate name of the feed `{}`", feed.url);
+            }
+        }
+        for webhook in &config.webhooks {
+            if let Some(url) = webhook.feeds.iter().find(|url| config.feed(url).is_none()) {
+                anyhow::bail!("the webhook feed `{}` is not polled", url);
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn feed(&self, url: &Url) -> Option<&Feed> {
+        self.feeds.iter().find(|feed| feed.url == *url)
+    }
+
+    pub fn poll_interval(&self, feed: &Feed) -> Duration {
+        feed.interval.unwrap_or_else(|| {
+            let source = transcode::Source::from_url(&feed.url).unwrap();
+            self.sources.get(source).poll_interval
+        })
+    }
+
+    /// Returns how long the last good feed of `url` may be served from the cache.
+    pub fn cache_lifetime(&self, source: transcode::Source, url: &Url) -> Duration {
+        let stale_if_error = self.sources.get(source).stale_if_error;
+        match self.feed(url) {
+            Some(feed) => self.poll_interval(feed) + stale_if_error,
+            None => stale_if_error,
+        }
+    }
+}
+
+impl Feed {
+    /// Returns the base name of the files of the feed written by `build`.
+    pub fn name(&self) -> String {
+        if let Some(ref name) = self.name {
+            return name.clone();
+        }
+        util::slug(&self.url)
+    }
+}
+
+impl Origins {
+    /// Returns the URL that the upstream `url` is fetched from.
+    pub fn map(&self, url: &Url) -> Url {
+        match self.0.get(&url.origin()) {
+            // `url` is absolute, so the path starts with a slash.
+            Some(base) => base.join(&url[url::Position::BeforePath..][1..]).unwrap(),
+            None => url.clone(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Origins {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let http = |url: &Url| matches!(url.scheme(), "http" | "https") && url.has_host();
+        BTreeMap::<String, Url>::deserialize(d)?
+            .into_iter()
+            .map(|(origin, base)| {
+                let url = origin.parse::<Url>().map_err(de::Error::custom)?;
+                if !http(&url) || url[url::Position::BeforePath..] != *"/" {
+                    let msg = format!("`{}` is not an HTTP(S) origin", origin);
+                    return Err(de::Error::custom(msg));
+                }
+                if !http(&base)
+                    || !base.path().ends_with('/')
+                    || base.query().is_some()
+                    || base.fragment().is_some()
+                {
+                    let msg = format!("`{}` is not an HTTP(S) URL ending with a slash", base);
+                    return Err(de::Error::custom(msg));
+                }
+                Ok((url.origin(), base))
+            })
+            .collect::<Result<_, _>>()
+            .map(Origins)
+    }
+}
+
+impl Cors {
+    pub fn allows(&self, origin: &[u8]) -> bool {
+        self.allow_origins
+            .iter()
+            .any(|o| o == "*" || o.as_bytes() == origin)
+    }
+
+    pub fn allows_any(&self) -> bool {
+        self.allow_origins.iter().any(|o| o == "*")
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            user_agent: concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).into(),
+            forward_headers: vec![ACCEPT_LANGUAGE, IF_MODIFIED_SINCE, IF_NONE_MATCH],
+            state_dir: None,
+            public_url: None,
+            origins: Origins::default(),
+            cors: Cors::default(),
+            websub: WebSub::default(),
+            metrics: false,
+            status: false,
+            log: Log::default(),
+            shutdown_timeout: Duration::from_secs(30),
+            sources: Sources::default(),
+            feeds: Vec::new(),
+            webhooks: Vec::new(),
+        }
+    }
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Log {
+            level: "info".into(),
+            format: LogFormat::Text,
+        }
+    }
+}
+
+impl Default for WebSub {
+    fn default() -> Self {
+        WebSub {
+            enabled: false,
+            default_lease: Duration::from_secs(10 * 24 * 60 * 60),
+            max_lease: Duration::from_secs(30 * 24 * 60 * 60),
+            timeout: Duration::from_secs(30),
+            max_subscriptions: 1000,
+            max_subscriptions_per_topic: 100,
+        }
+    }
+}
+
+impl Webhook {
+    pub fn notifies(&self, url: &Url) -> bool {
+        self.feeds.is_empty() || self.feeds.contains(url)
+    }
+}
+
+impl Sources {
+    pub fn get(&self, source: transcode::Source) -> &Source {
+        match source {
+            transcode::Source::KemonoFriendsSegaJp => &self.kemono_friends_sega_jp,
+            transcode::Source::KadokawaCoJp => &self.kadokawa_co_jp,
+            transcode::Source::JvcmusicCoJp => &self.jvcmusic_co_jp,
+        }
+    }
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Source {
+            connect_timeout: Duration::from_secs(10),
+            timeout: Duration::from_secs(60),
+            retries: 2,
+            retry_backoff: Duration::from_millis(500),
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(60),
+            stale_if_error: Duration::from_secs(24 * 60 * 60),
+            poll_interval: Duration::from_secs(10 * 60),
+            ttl: Duration::from_secs(10 * 60),
+            headers: HeaderMap::new(),
+            validate: Validate::Off,
+        }
+    }
+}
+
+fn default_webhook_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_webhook_retries() -> u32 {
+    3
+}
+
+fn default_webhook_retry_backoff() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_webhook_max_backoff() -> Duration {
+    Duration::from_secs(5 * 60)
+}
+
+fn seconds<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+    let secs = f64::deserialize(d)?;
+    Duration::try_from_secs_f64(secs)
+        .map_err(|_| de::Error::custom("expected a non-negative number of seconds"))
+}
+
+fn optional_seconds<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+    seconds(d).map(Some)
+}
+
+fn header_names<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<HeaderName>, D::Error> {
+    Vec::<String>::deserialize(d)?
+        .iter()
+        .map(|name| name.parse().map_err(de::Error::custom))
+        .collect()
+}
+
+fn header_map<'de, D: Deserializer<'de>>(d: D) -> Result<HeaderMap, D::Error> {
+    BTreeMap::<String, String>::deserialize(d)?
+        .iter()
+        .map(|(name, value)| {
+            let name = name.parse::<HeaderName>().map_err(de::Error::custom)?;
+            let value = value.parse::<HeaderValue>().map_err(de::Error::custom)?;
+            Ok((name, value))
+        })
+        .collect()
+}
@@ -0,0 +1,61 @@
+use hyper::{
+    header::{
+        HeaderName, HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+        ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE, VARY,
+    },
+    Body, Response, StatusCode,
+};
+
+use crate::{config, logging::REQUEST_ID};
+
+/// Adds the CORS headers for a request from `origin` to `res`.
+pub fn apply(config: &config::Cors, origin: Option<&HeaderValue>, res: &mut Response<Body>) {
+    let headers = res.headers_mut();
+
+    let any = config.allows_any();
+    if !any && !config.allow_origins.is_empty() {
+        headers.append(VARY, HeaderValue::from_static("Origin"));
+    }
+
+    let origin = match origin {
+        Some(origin) if config.allows(origin.as_bytes()) => origin,
+        _ => return,
+    };
+
+    let allow_origin = if any {
+        HeaderValue::from_static("*")
+    } else {
+        origin.clone()
+    };
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+    let mut expose_headers = vec![HeaderName::from_static(REQUEST_ID)];
+    expose_headers.extend(
+        config
+            .expose_headers
+            .iter()
+            .filter(|name| *name != REQUEST_ID)
+            .cloned(),
+    );
+    headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, join(&expose_headers));
+}
+
+/// Returns a response to a CORS preflight request.
+///
+/// The `Access-Control-Allow-Origin` header is left to `apply`.
+pub fn preflight(config: &config::Cors) -> Response<Body> {
+    let mut res = Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header(ACCESS_CONTROL_ALLOW_METHODS, "GET, HEAD");
+    if !config.allow_headers.is_empty() {
+        res = res.header(ACCESS_CONTROL_ALLOW_HEADERS, join(&config.allow_headers));
+    }
+    if let Some(max_age) = config.max_age {
+        res = res.header(ACCESS_CONTROL_MAX_AGE, max_age);
+    }
+    res.body(Body::empty()).unwrap()
+}
+
+fn join(names: &[HeaderName]) -> HeaderValue {
+    let names = names.iter().map(HeaderName::as_str).collect::<Vec<_>>();
+    HeaderValue::from_str(&names.join(", ")).unwrap()
+}
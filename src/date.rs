@@ -0,0 +1,288 @@
+//! Parsing of the upstream dates and conversions of the RFC 3339 date-times in the feeds.
+
+use std::{
+    borrow::Cow,
+    fmt::{self, Display, Formatter},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(test)]
+mod tests;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+
+/// Offset of Japan Standard Time, in seconds.
+const JST: i64 = 9 * 60 * 60;
+
+/// An upstream date that none of the known formats matches.
+#[derive(Debug)]
+pub struct Error(String);
+
+/// Formats `time` as an RFC 3339 date-time in JST.
+pub fn format_jst(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    format_jst_secs(secs as i64)
+}
+
+/// Normalizes an upstream date or date-time to an RFC 3339 date-time in JST.
+pub fn normalize(date: &str) -> Result<String, Error> {
+    normalize_secs(date.trim())
+        .map(format_jst_secs)
+        .ok_or_else(|| Error(date.to_owned()))
+}
+
+fn normalize_secs(date: &str) -> Option<i64> {
+    let mut c = Cursor(date.as_bytes());
+
+    let year = c.number(4, 4)?;
+    let separator = c.next().filter(|&b| b == b'-' || b == b'/')?;
+    let month = c.number(1, 2)?;
+    c.expect(separator)?;
+    let day = c.number(1, 2)?;
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    let (mut hour, mut minute, mut second, mut offset) = (0, 0, 0, JST);
+    if !c.0.is_empty() {
+        c.next().filter(|&b| matches!(b, b'T' | b't' | b' '))?;
+        hour = c.number(1, 2)?;
+        c.expect(b':')?;
+        minute = c.number(2, 2)?;
+        if c.0.first() == Some(&b':') {
+            c.next();
+            second = c.number(2, 2)?;
+            // A leap second is folded into the second before it.
+            if second == 60 {
+                second = 59;
+            }
+            if c.0.first() == Some(&b'.') {
+                c.next();
+                c.number(1, usize::MAX)?;
+            }
+        }
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        match c.next() {
+            None => {}
+            Some(b'Z') | Some(b'z') => offset = 0,
+            Some(sign @ b'+') | Some(sign @ b'-') => {
+                let hours = c.number(2, 2)?;
+                if c.0.first() == Some(&b':') {
+                    c.next();
+                }
+                let minutes = c.number(2, 2)?;
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                offset = i64::from(hours * 60 + minutes) * 60;
+                if sign == b'-' {
+                    offset = -offset;
+                }
+            }
+            Some(_) => return None,
+        }
+    }
+    if !c.0.is_empty() {
+        return None;
+    }
+
+    let days = days_from_civil(i64::from(year), month, day);
+    let secs = i64::from(hour * 3600 + minute * 60 + second);
+    Some(days * 86400 + secs - offset)
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 date-time in JST.
+fn format_jst_secs(secs: i64) -> String {
+    let secs = secs + JST;
+    let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}+09:00",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
+
+struct Cursor<'a>(&'a [u8]);
+
+impl Cursor<'_> {
+    fn next(&mut self) -> Option<u8> {
+        let (&b, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(b)
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        self.next().filter(|&next| next == b).map(drop)
+    }
+
+    /// Reads a decimal number of `min` to `max` digits.
+    fn number(&mut self, min: usize, max: usize) -> Option<u32> {
+        let len = self
+            .0
+            .iter()
+            .take(max)
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if len < min {
+            return None;
+        }
+        let (digits, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(digits.iter().fold(0u32, |n, &d| {
+            n.saturating_mul(10).saturating_add(u32::from(d - b'0'))
+        }))
+    }
+}
+
+/// Returns whether `date` is an RFC 3339 date-time in the uppercase form that RFC 4287 requires.
+pub fn is_rfc3339(date: &str) -> bool {
+    parse(date).is_some() && !date.contains(['t', 'z', ' '])
+}
+
+/// Converts an RFC 3339 date-time to the RFC 2822 format used by RSS, keeping its offset.
+pub fn to_rfc2822(date: &str) -> Option<String> {
+    let d = parse(date)?;
+    let weekday =
+        WEEKDAYS[days_from_civil(i64::from(d.year), d.month, d.day).rem_euclid(7) as usize];
+    Some(format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} {}",
+        weekday,
+        d.day,
+        MONTHS[d.month as usize - 1],
+        d.year,
+        d.hour,
+        d.minute,
+        d.second,
+        d.offset,
+    ))
+}
+
+struct DateTime<'a> {
+    year: u32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    /// The offset in the RFC 2822 form, e.g. `+0900`.
+    offset: Cow<'a, str>,
+}
+
+/// Parses an RFC 3339 date-time, also accepting the lowercase and space-separated forms.
+fn parse(date: &str) -> Option<DateTime<'_>> {
+    let b = date.as_bytes();
+    let num = |range: std::ops::Range<usize>| -> Option<u32> {
+        let s = date.get(range)?;
+        if s.bytes().all(|c| c.is_ascii_digit()) {
+            s.parse().ok()
+        } else {
+            None
+        }
+    };
+
+    let (year, month, day) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (hour, minute, second) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    if b.get(4) != Some(&b'-')
+        || b.get(7) != Some(&b'-')
+        || !matches!(b.get(10), Some(b'T') | Some(b't') | Some(b' '))
+        || b.get(13) != Some(&b':')
+        || b.get(16) != Some(&b':')
+        || !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        // Leap seconds.
+        || second > 60
+    {
+        return None;
+    }
+
+    let mut rest = &date[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        rest = &fraction[digits..];
+    }
+    let offset = match *rest.as_bytes() {
+        [b'Z'] | [b'z'] => "+0000".into(),
+        [sign @ b'+', h1, h2, b':', m1, m2] | [sign @ b'-', h1, h2, b':', m1, m2] => {
+            let digits = [h1, h2, m1, m2];
+            if !digits.iter().all(u8::is_ascii_digit) || [h1, h2] > *b"23" || [m1, m2] > *b"59" {
+                return None;
+            }
+            let mut offset = vec![sign];
+            offset.extend_from_slice(&digits);
+            String::from_utf8(offset).unwrap().into()
+        }
+        _ => return None,
+    };
+
+    Some(DateTime {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+        offset,
+    })
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    let (year, next_year) = (i64::from(year), i64::from(next_year));
+    (days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)) as u32
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unrecognized date `{}`", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+// <http://howardhinnant.github.io/date_algorithms.html>
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (i64::from(month) + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
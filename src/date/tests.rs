@@ -0,0 +1,66 @@
+//! Tests of the date parsing and conversions.
+
+use super::{is_rfc3339, normalize, to_rfc2822};
+
+#[test]
+fn normalizes_upstream_formats() {
+    let cases = [
+        // kemono-friends-sega-jp
+        ("2021-09-24T12:00:00", "2021-09-24T12:00:00+09:00"),
+        // kadokawa-co-jp
+        ("2021-10-26", "2021-10-26T00:00:00+09:00"),
+        // jvcmusic-co-jp
+        ("2021-09-24 12:00:00", "2021-09-24T12:00:00+09:00"),
+        ("2021/9/4 9:05", "2021-09-04T09:05:00+09:00"),
+        (" 2021-09-24T12:00:00.123 ", "2021-09-24T12:00:00+09:00"),
+        ("2021-09-24T12:00:00+09:00", "2021-09-24T12:00:00+09:00"),
+        ("2021-09-24T12:00:00+0900", "2021-09-24T12:00:00+09:00"),
+        ("2021-12-31T20:30:00Z", "2022-01-01T05:30:00+09:00"),
+        ("2021-03-01T01:00:00-05:00", "2021-03-01T15:00:00+09:00"),
+        ("2016-12-31T23:59:60+09:00", "2016-12-31T23:59:59+09:00"),
+    ];
+    for &(input, expected) in &cases {
+        assert_eq!(normalize(input).unwrap(), expected, "{}", input);
+        assert!(is_rfc3339(expected));
+    }
+}
+
+#[test]
+fn rejects_unknown_formats() {
+    for &input in &[
+        "",
+        "2021",
+        "2021-09",
+        "2021-09-24T",
+        "2021-09-24T12",
+        "2021-13-01",
+        "2021-02-29",
+        "2021-09-31",
+        "2021-09-24T24:00:00",
+        "2021-09-24T12:00:75",
+        "2021-09-24T12:00:99+09:00",
+        "2021-09-24T12:00:00+25:00",
+        "2021-09-24T12:00:00 JST",
+        "2021-09/24",
+        "24/09/2021",
+        "令和3年9月24日",
+    ] {
+        let e = normalize(input).unwrap_err();
+        assert_eq!(e.to_string(), format!("unrecognized date `{}`", input));
+    }
+}
+
+#[test]
+fn converts_to_rfc2822() {
+    assert_eq!(
+        to_rfc2822("2021-09-24T12:00:00.5+09:00").unwrap(),
+        "Fri, 24 Sep 2021 12:00:00 +0900",
+    );
+    assert_eq!(
+        to_rfc2822("2000-02-29t00:00:00z").unwrap(),
+        "Tue, 29 Feb 2000 00:00:00 +0000",
+    );
+    assert!(!is_rfc3339("2000-02-29t00:00:00z"));
+    assert_eq!(to_rfc2822("2021-09-24"), None);
+    assert_eq!(to_rfc2822("2021-09-24T12:00:00あ:00"), None);
+}
@@ -0,0 +1,273 @@
+//! A model of the Atom feeds produced by the transcoders.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    io::Write,
+    mem,
+};
+
+use xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+
+use crate::{date, util};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Feed {
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub link: Option<String>,
+    pub updated: Option<String>,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Entry {
+    pub id: String,
+    pub title: String,
+    pub link: Option<String>,
+    pub published: Option<String>,
+    pub updated: Option<String>,
+    pub content: Option<String>,
+    pub authors: Vec<String>,
+    pub categories: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Error(xml::Error);
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Feed {
+    /// Parses an Atom document as written by the transcoders.
+    pub fn parse(input: &[u8]) -> Result<Self> {
+        let mut reader = xml::Reader::from_reader(input);
+        let mut buf = Vec::new();
+        let mut feed = Feed::default();
+        let mut entry: Option<Entry> = None;
+        let mut text = String::new();
+
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(ref e) => {
+                    if e.name() == b"entry" {
+                        entry = Some(Entry::default());
+                    }
+                    text.clear();
+                }
+                Event::Empty(ref e) => {
+                    let attr = |name| util::attr(&reader, e, name);
+                    match (e.name(), &mut entry) {
+                        (b"link", Some(entry)) => entry.link = attr(b"href")?,
+                        (b"link", None) => feed.link = attr(b"href")?,
+                        (b"category", Some(entry)) => entry.categories.extend(attr(b"term")?),
+                        _ => {}
+                    }
+                }
+                Event::Text(ref e) => text.push_str(&e.unescape_and_decode(&reader)?),
+                Event::CData(ref e) => text.push_str(&reader.decode(e.escaped())),
+                Event::End(ref e) => {
+                    let text = mem::take(&mut text);
+                    match (e.name(), &mut entry) {
+                        (b"entry", _) => feed.entries.extend(entry.take()),
+                        (b"id", Some(entry)) => entry.id = text,
+                        (b"title", Some(entry)) => entry.title = text,
+                        (b"published", Some(entry)) => entry.published = Some(text),
+                        (b"updated", Some(entry)) => entry.updated = Some(text),
+                        (b"content", Some(entry)) => entry.content = Some(text),
+                        (b"name", Some(entry)) => entry.authors.push(text),
+                        (b"id", None) => feed.id = text,
+                        (b"title", None) => feed.title = text,
+                        (b"subtitle", None) => feed.subtitle = Some(text),
+                        (b"updated", None) => feed.updated = Some(text),
+                        _ => {}
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        Ok(feed)
+    }
+
+    /// Rewrites the `<updated>` elements of `atom`, the document that `self` was parsed from, to
+    /// the dates in `self`, leaving the rest of the document byte for byte.
+    pub fn rewrite_updated(&self, atom: &[u8]) -> Result<Vec<u8>> {
+        let mut reader = xml::Reader::from_reader(atom);
+        let mut writer = xml::Writer::new(Vec::with_capacity(atom.len()));
+        let mut buf = Vec::new();
+        let mut depth = 0;
+        let mut entries = self.entries.iter();
+        // The entry being read, and whether its `<updated>` has been written.
+        let mut entry: Option<(&Entry, bool)> = None;
+        let mut skip = false;
+
+        loop {
+            let event = reader.read_event(&mut buf)?;
+            match event {
+                Event::Start(ref e) => {
+                    depth += 1;
+                    match (depth, e.name()) {
+                        (2, b"entry") => entry = entries.next().map(|entry| (entry, false)),
+                        (2, b"updated") => skip = self.updated.is_some(),
+                        (3, b"updated") => {
+                            if let Some((entry, ref mut written)) = entry {
+                                skip = entry.updated.is_some();
+                                *written = true;
+                            }
+                        }
+                        _ => {}
+                    }
+                    writer.write_event(&event)?;
+                    if skip {
+                        let updated = match entry {
+                            Some((entry, _)) if depth == 3 => &entry.updated,
+                            _ => &self.updated,
+                        };
+                        let updated = updated.as_deref().unwrap_or_default();
+                        writer.write_event(Event::Text(BytesText::from_plain_str(updated)))?;
+                    }
+                }
+                Event::End(ref e) => {
+                    if depth == 2 && e.name() == b"entry" {
+                        if let Some((entry, false)) = entry.take() {
+                            if let Some(ref updated) = entry.updated {
+                                util::write_text(&mut writer, b"updated", updated)?;
+                            }
+                        }
+                    }
+                    depth -= 1;
+                    skip = false;
+                    writer.write_event(&event)?;
+                }
+                Event::Eof => break,
+                _ if skip => {}
+                _ => {
+                    writer.write_event(&event)?;
+                }
+            }
+            buf.clear();
+        }
+
+        Ok(writer.into_inner())
+    }
+
+    /// Writes the feed as RSS 2.0.
+    pub fn write_rss<W: Write>(&self, w: W) -> Result<()> {
+        let mut writer = xml::Writer::new(w);
+        writer.write_event(Event::Decl(BytesDecl::new(b"1.0", Some(b"utf-8"), None)))?;
+        let start = BytesStart::borrowed(
+            br#"rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/""#,
+            3,
+        );
+        writer.write_event(Event::Start(start))?;
+        writer.write_event(Event::Start(BytesStart::borrowed_name(b"channel")))?;
+
+        util::write_text(&mut writer, b"title", &self.title)?;
+        util::write_text(&mut writer, b"link", self.link.as_deref().unwrap_or(""))?;
+        let description = self.subtitle.as_ref().unwrap_or(&self.title);
+        util::write_text(&mut writer, b"description", description)?;
+        if let Some(date) = self.updated.as_deref().and_then(date::to_rfc2822) {
+            util::write_text(&mut writer, b"lastBuildDate", &date)?;
+        }
+
+        for entry in &self.entries {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"item")))?;
+            util::write_text(&mut writer, b"title", &entry.title)?;
+            if let Some(ref href) = entry.link {
+                util::write_text(&mut writer, b"link", href)?;
+            }
+            let mut guid = BytesStart::borrowed_name(b"guid");
+            guid.push_attribute(("isPermaLink", "false"));
+            writer.write_event(Event::Start(guid))?;
+            writer.write_event(Event::Text(BytesText::from_plain_str(&entry.id)))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"guid")))?;
+            if let Some(ref content) = entry.content {
+                util::write_text(&mut writer, b"description", content)?;
+            }
+            for name in &entry.authors {
+                util::write_text(&mut writer, b"dc:creator", name)?;
+            }
+            for term in &entry.categories {
+                util::write_text(&mut writer, b"category", term)?;
+            }
+            if let Some(date) = entry.published.as_deref().and_then(date::to_rfc2822) {
+                util::write_text(&mut writer, b"pubDate", &date)?;
+            }
+            writer.write_event(Event::End(BytesEnd::borrowed(b"item")))?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::borrowed(b"channel")))?;
+        writer.write_event(Event::End(BytesEnd::borrowed(b"rss")))?;
+        Ok(())
+    }
+
+    pub fn to_rss(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.write_rss(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Returns the feed as a JSON Feed 1.1 document.
+    pub fn to_json_feed(&self, feed_url: Option<&str>) -> json::Value {
+        let items = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let authors = entry
+                    .authors
+                    .iter()
+                    .map(|name| json::json!({ "name": name }))
+                    .collect::<Vec<_>>();
+                let mut item = json::json!({
+                    "id": entry.id,
+                    "url": entry.link,
+                    "title": entry.title,
+                    "content_text": entry.content,
+                    "date_published": entry.published,
+                    "date_modified": entry.updated,
+                    "authors": if authors.is_empty() { None } else { Some(authors) },
+                    "tags": if entry.categories.is_empty() { None } else { Some(&entry.categories) },
+                });
+                strip_nulls(&mut item);
+                item
+            })
+            .collect::<Vec<_>>();
+
+        let mut feed = json::json!({
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": self.title,
+            "home_page_url": self.link,
+            "feed_url": feed_url,
+            "description": self.subtitle,
+            "items": items,
+        });
+        strip_nulls(&mut feed);
+        feed
+    }
+}
+
+/// Removes the optional members that are absent, as JSON Feed does not allow them to be `null`.
+fn strip_nulls(value: &mut json::Value) {
+    if let Some(object) = value.as_object_mut() {
+        object.retain(|_, value| !value.is_null());
+    }
+}
+
+impl From<xml::Error> for Error {
+    fn from(e: xml::Error) -> Self {
+        Error(e)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,69 @@
+//! Tests of the rewriting and conversions of Atom documents.
+
+use super::Feed;
+
+const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>Example</title><link rel="alternate" href="https://example.com/"/><author><name>Example</name></author><updated>2021-09-25T18:30:00+09:00</updated><id>tag:example.com,2021:feed</id><entry><id>tag:example.com,2021:1</id><link href="https://example.com/1"/><title>First</title><author><name>Alice</name></author><category term="news"/><content type="text"><![CDATA[<p>A &amp; B</p>]]></content><published>2021-09-24T12:00:00+09:00</published><updated>2021-09-25T18:30:00+09:00</updated></entry><entry><id>tag:example.com,2021:2</id><title>Second</title></entry></feed>"#;
+
+#[test]
+fn rewrites_only_dates() {
+    let mut feed = Feed::parse(ATOM.as_bytes()).unwrap();
+    feed.updated = Some("2021-09-26T00:00:00+09:00".to_owned());
+    feed.entries[0].updated = Some("2021-09-26T00:00:00+09:00".to_owned());
+    feed.entries[1].updated = Some("2021-09-24T00:00:00+09:00".to_owned());
+
+    let atom = feed.rewrite_updated(ATOM.as_bytes()).unwrap();
+    let expected = ATOM
+        .replace("2021-09-25T18:30:00+09:00", "2021-09-26T00:00:00+09:00")
+        .replace(
+            "<title>Second</title></entry>",
+            "<title>Second</title><updated>2021-09-24T00:00:00+09:00</updated></entry>",
+        );
+    assert_eq!(String::from_utf8(atom).unwrap(), expected);
+}
+
+#[test]
+fn converts_to_rss() {
+    let feed = Feed::parse(ATOM.as_bytes()).unwrap();
+    let rss = String::from_utf8(feed.to_rss().unwrap()).unwrap();
+    let expected = concat!(
+        r#"<?xml version="1.0" encoding="utf-8"?>"#,
+        r#"<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/"><channel>"#,
+        "<title>Example</title><link>https://example.com/</link><description>Example</description>",
+        "<lastBuildDate>Sat, 25 Sep 2021 18:30:00 +0900</lastBuildDate>",
+        "<item><title>First</title><link>https://example.com/1</link>",
+        r#"<guid isPermaLink="false">tag:example.com,2021:1</guid>"#,
+        "<description>&lt;p&gt;A &amp;amp; B&lt;/p&gt;</description>",
+        "<dc:creator>Alice</dc:creator><category>news</category>",
+        "<pubDate>Fri, 24 Sep 2021 12:00:00 +0900</pubDate></item>",
+        r#"<item><title>Second</title><guid isPermaLink="false">tag:example.com,2021:2</guid></item>"#,
+        "</channel></rss>",
+    );
+    assert_eq!(rss, expected);
+}
+
+#[test]
+fn converts_to_json_feed() {
+    let feed = Feed::parse(ATOM.as_bytes()).unwrap();
+    let json_feed = feed.to_json_feed(Some("https://feeds.example.com/example.json"));
+    let expected = json::json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": "Example",
+        "home_page_url": "https://example.com/",
+        "feed_url": "https://feeds.example.com/example.json",
+        "items": [
+            {
+                "id": "tag:example.com,2021:1",
+                "url": "https://example.com/1",
+                "title": "First",
+                "content_text": "<p>A &amp; B</p>",
+                "date_published": "2021-09-24T12:00:00+09:00",
+                "date_modified": "2021-09-25T18:30:00+09:00",
+                "authors": [{ "name": "Alice" }],
+                "tags": ["news"],
+            },
+            { "id": "tag:example.com,2021:2", "title": "Second" },
+        ],
+    });
+    assert_eq!(json_feed, expected);
+}
@@ -0,0 +1,91 @@
+//! Liveness and readiness probes for orchestrators, served at `/healthz` and `/readyz`.
+
+use std::{collections::HashMap, fmt::Write as _, sync::Mutex, time::SystemTime};
+
+use hyper::{
+    header::{CACHE_CONTROL, CONTENT_TYPE},
+    Body, Response, StatusCode,
+};
+use reqwest::Url;
+
+use crate::{router::State, transcode::Source};
+
+pub const LIVE_PATH: &str = "/healthz";
+pub const READY_PATH: &str = "/readyz";
+
+/// Outcomes of the background polls of each feed.
+#[derive(Default)]
+pub struct Polls(Mutex<HashMap<Url, Poll>>);
+
+#[derive(Clone, Debug, Default)]
+pub struct Poll {
+    /// When the last successful poll completed.
+    pub success: Option<SystemTime>,
+    /// The error of the last poll, if it failed.
+    pub error: Option<String>,
+}
+
+impl Polls {
+    pub fn succeed(&self, url: &Url) {
+        let mut polls = self.0.lock().unwrap();
+        let poll = polls.entry(url.clone()).or_default();
+        poll.success = Some(SystemTime::now());
+        poll.error = None;
+    }
+
+    pub fn fail(&self, url: &Url, error: &anyhow::Error) {
+        let mut polls = self.0.lock().unwrap();
+        polls.entry(url.clone()).or_default().error = Some(error.to_string());
+    }
+
+    pub fn get(&self, url: &Url) -> Poll {
+        self.0.lock().unwrap().get(url).cloned().unwrap_or_default()
+    }
+}
+
+/// Reports that the process is alive.
+pub fn live() -> Response<Body> {
+    respond(StatusCode::OK, "ok\n".to_owned())
+}
+
+/// Reports whether every polled feed was polled successfully within its interval plus
+/// `stale-if-error`.
+pub fn ready(state: &State) -> Response<Body> {
+    let mut failures = String::new();
+    for feed in &state.config.feeds {
+        let source = Source::from_url(&feed.url).unwrap();
+        let window =
+            state.config.poll_interval(feed) + state.config.sources.get(source).stale_if_error;
+        let poll = state.polls.get(&feed.url);
+        let age = poll.success.map(|time| time.elapsed().unwrap_or_default());
+        if age.is_some_and(|age| age <= window) {
+            continue;
+        }
+        write!(failures, "{} {}: ", source.name(), feed.url).unwrap();
+        match age {
+            Some(age) => {
+                write!(failures, "last polled successfully {}s ago", age.as_secs()).unwrap()
+            }
+            None => failures.push_str("never polled successfully"),
+        }
+        if let Some(ref error) = poll.error {
+            write!(failures, " ({})", error).unwrap();
+        }
+        failures.push('\n');
+    }
+
+    if failures.is_empty() {
+        respond(StatusCode::OK, "ok\n".to_owned())
+    } else {
+        respond(StatusCode::SERVICE_UNAVAILABLE, failures)
+    }
+}
+
+fn respond(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(CACHE_CONTROL, "no-store")
+        .body(Body::from(body))
+        .unwrap()
+}
@@ -0,0 +1,114 @@
+//! Bumps `<updated>` of the entries whose content has changed since the last poll.
+
+use std::{collections::BTreeMap, time::SystemTime};
+
+use bytes::Bytes;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::Config,
+    date,
+    feed::{Entry, Feed},
+    util::{self, Persisted},
+};
+
+#[cfg(test)]
+mod tests;
+
+pub struct History {
+    feeds: Persisted<BTreeMap<Url, BTreeMap<String, Record>>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Record {
+    hash: String,
+    /// The `<updated>` of the entry when it was last seen.
+    updated: Option<String>,
+}
+
+impl History {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let feeds = Persisted::load(config.state_dir.as_deref(), "history.json")?;
+        Ok(History { feeds })
+    }
+
+    pub async fn save(&self) -> anyhow::Result<()> {
+        self.feeds.save().await
+    }
+
+    /// Returns `atom`, the document `feed` was parsed from, with the bumped dates.
+    pub async fn apply(&self, url: &Url, feed: &mut Feed, atom: Bytes) -> anyhow::Result<Bytes> {
+        let now = date::format_jst(SystemTime::now());
+        let (changed, bumped) = {
+            let mut feeds = self.feeds.lock();
+            bump(feeds.entry(url.clone()).or_default(), feed, &now)
+        };
+
+        if changed {
+            self.feeds.save().await?;
+        }
+
+        if !bumped {
+            return Ok(atom);
+        }
+        let latest = feed.entries.iter().filter_map(|e| e.updated.clone()).max();
+        if latest > feed.updated {
+            feed.updated = latest;
+        }
+        Ok(feed.rewrite_updated(&atom)?.into())
+    }
+}
+
+/// Bumps the entries of `feed` whose content differs from their `records` to `now`, forgetting
+/// the entries no longer in the feed.
+///
+/// Returns whether `records` have changed and whether any entry was bumped.
+fn bump(records: &mut BTreeMap<String, Record>, feed: &mut Feed, now: &str) -> (bool, bool) {
+    let len = records.len();
+    records.retain(|id, _| feed.entries.iter().any(|entry| entry.id == *id));
+    let mut changed = records.len() != len;
+    let mut bumped = false;
+    for entry in &mut feed.entries {
+        let hash = hash(entry);
+        match records.get_mut(&entry.id) {
+            Some(record) if record.hash == hash => {
+                // Keep the bumped date unless the upstream reports a later one.
+                if record.updated > entry.updated {
+                    entry.updated = record.updated.clone();
+                    bumped = true;
+                }
+            }
+            Some(record) => {
+                if entry.updated.as_deref() < Some(now) {
+                    entry.updated = Some(now.to_owned());
+                    bumped = true;
+                }
+                record.hash = hash;
+                record.updated = entry.updated.clone();
+                changed = true;
+            }
+            None => {
+                let record = Record {
+                    hash,
+                    updated: entry.updated.clone(),
+                };
+                records.insert(entry.id.clone(), record);
+                changed = true;
+            }
+        }
+    }
+    (changed, bumped)
+}
+
+/// Hashes the parts of `entry` that make up its content.
+fn hash(entry: &Entry) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(entry.title.as_bytes());
+    hasher.update([0]);
+    if let Some(ref content) = entry.content {
+        hasher.update(content.as_bytes());
+    }
+    util::hex(&hasher.finalize()[..16])
+}
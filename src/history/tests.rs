@@ -0,0 +1,102 @@
+//! Tests of the bumping of the dates of changed entries.
+
+use std::collections::BTreeMap;
+
+use super::bump;
+use crate::feed::{Entry, Feed};
+
+const NOW: &str = "2021-09-25T12:00:00+09:00";
+
+fn feed(entries: &[(&str, &str, &str)]) -> Feed {
+    Feed {
+        entries: entries
+            .iter()
+            .map(|&(id, title, updated)| Entry {
+                id: id.to_owned(),
+                title: title.to_owned(),
+                updated: Some(updated.to_owned()),
+                ..Entry::default()
+            })
+            .collect(),
+        ..Feed::default()
+    }
+}
+
+fn updated(feed: &Feed) -> Vec<&str> {
+    let entries = feed.entries.iter();
+    entries.map(|e| e.updated.as_deref().unwrap()).collect()
+}
+
+#[test]
+fn bumps_changed_entries() {
+    let mut records = BTreeMap::new();
+    let mut first = feed(&[
+        ("a", "A", "2021-09-24T12:00:00+09:00"),
+        ("b", "B", "2021-09-23T12:00:00+09:00"),
+    ]);
+    assert_eq!(bump(&mut records, &mut first, NOW), (true, false));
+    assert_eq!(
+        updated(&first),
+        ["2021-09-24T12:00:00+09:00", "2021-09-23T12:00:00+09:00"],
+    );
+
+    // The title of `b` has changed without its upstream date.
+    let mut second = feed(&[
+        ("a", "A", "2021-09-24T12:00:00+09:00"),
+        ("b", "B'", "2021-09-23T12:00:00+09:00"),
+    ]);
+    assert_eq!(bump(&mut records, &mut second, NOW), (true, true));
+    assert_eq!(updated(&second), ["2021-09-24T12:00:00+09:00", NOW]);
+
+    // An upstream date later than now is kept.
+    let mut third = feed(&[
+        ("a", "A'", "2021-09-26T12:00:00+09:00"),
+        ("b", "B'", "2021-09-23T12:00:00+09:00"),
+    ]);
+    assert_eq!(bump(&mut records, &mut third, NOW), (true, true));
+    assert_eq!(updated(&third), ["2021-09-26T12:00:00+09:00", NOW]);
+}
+
+#[test]
+fn keeps_bumped_dates_of_unchanged_entries() {
+    let mut records = BTreeMap::new();
+    let mut first = feed(&[("a", "A", "2021-09-24T12:00:00+09:00")]);
+    bump(&mut records, &mut first, "2021-09-24T13:00:00+09:00");
+    let mut second = feed(&[("a", "A'", "2021-09-24T12:00:00+09:00")]);
+    bump(&mut records, &mut second, "2021-09-24T13:00:00+09:00");
+
+    // The upstream still reports the original date of the unchanged entry.
+    let mut third = feed(&[("a", "A'", "2021-09-24T12:00:00+09:00")]);
+    assert_eq!(bump(&mut records, &mut third, NOW), (false, true));
+    assert_eq!(updated(&third), ["2021-09-24T13:00:00+09:00"]);
+
+    // A later upstream date takes over.
+    let mut fourth = feed(&[("a", "A'", "2021-09-25T00:00:00+09:00")]);
+    assert_eq!(bump(&mut records, &mut fourth, NOW), (false, false));
+    assert_eq!(updated(&fourth), ["2021-09-25T00:00:00+09:00"]);
+}
+
+#[test]
+fn forgets_removed_entries() {
+    let mut records = BTreeMap::new();
+    let mut first = feed(&[
+        ("a", "A", "2021-09-24T12:00:00+09:00"),
+        ("b", "B", "2021-09-23T12:00:00+09:00"),
+    ]);
+    bump(&mut records, &mut first, NOW);
+
+    let mut second = feed(&[("a", "A", "2021-09-24T12:00:00+09:00")]);
+    assert_eq!(bump(&mut records, &mut second, NOW), (true, false));
+    assert_eq!(records.keys().collect::<Vec<_>>(), ["a"]);
+
+    // An entry coming back is recorded anew rather than bumped.
+    let mut third = feed(&[
+        ("a", "A", "2021-09-24T12:00:00+09:00"),
+        ("b", "B'", "2021-09-23T12:00:00+09:00"),
+    ]);
+    assert_eq!(bump(&mut records, &mut third, NOW), (true, false));
+    assert_eq!(
+        updated(&third),
+        ["2021-09-24T12:00:00+09:00", "2021-09-23T12:00:00+09:00"],
+    );
+}
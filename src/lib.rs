@@ -0,0 +1,214 @@
+//! A Web application that converts Kemono Friends-related Web pages to Atom feeds.
+//!
+//! The binary only calls `run`. The transcoders and the feed parser are exposed to the fuzz
+//! targets.
+
+#[macro_use]
+mod util;
+
+mod build;
+mod cache;
+mod compress;
+mod config;
+mod cors;
+mod date;
+#[doc(hidden)]
+pub mod feed;
+mod health;
+mod history;
+mod logging;
+mod metrics;
+mod router;
+mod scheduler;
+mod shutdown;
+mod status;
+mod tape;
+#[doc(hidden)]
+pub mod transcode;
+mod upstream;
+mod validate;
+mod webhook;
+mod websub;
+
+use std::{
+    convert::Infallible,
+    fs,
+    io::{self, Read, Write},
+    net::ToSocketAddrs,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use futures::{
+    channel::oneshot,
+    future::{self, Either},
+    stream,
+};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Server,
+};
+use reqwest::Url;
+use structopt::StructOpt;
+use tokio::time::{self, Instant};
+use tracing::{error, info, warn};
+
+use crate::{config::Config, router::State, tape::Tape, transcode::Source};
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "kf-pipitor",
+    about = "A Web application that converts Kemono Friends-related Web pages to Atom feeds."
+)]
+struct Opt {
+    /// Host name for the HTTP server
+    #[structopt(default_value = "127.0.0.1")]
+    host: String,
+    /// Port number for the HTTP server
+    #[structopt(short = "p", long = "port", default_value = "8080")]
+    port: u16,
+    /// Path to the configuration file
+    #[structopt(short = "c", long = "config", parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Saves every upstream response and produced feed into a directory
+    #[structopt(long = "record", parse(from_os_str), conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// Serves upstream responses saved by `--record` instead of fetching them
+    #[structopt(long = "replay", parse(from_os_str))]
+    replay: Option<PathBuf>,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Transcodes an upstream JSON document and writes the feed to the standard output
+    Transcode {
+        /// Name of the transcoder, e.g. `kemono-friends-sega-jp`
+        #[structopt(short = "s", long = "source")]
+        source: Source,
+        /// Upstream URL of the document. Defaults to the main feed of the source
+        #[structopt(short = "u", long = "url")]
+        url: Option<Url>,
+        /// Path to the document. Reads the standard input if omitted
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+    },
+    /// Fetches the configured feeds once and writes them into a directory for static hosting
+    Build {
+        /// Output directory
+        #[structopt(short = "o", long = "out", parse(from_os_str))]
+        out: PathBuf,
+    },
+}
+
+/// Runs the command given on the command line.
+pub async fn run() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    let config = match opt.config {
+        Some(ref path) => Config::from_path(path)?,
+        None => Config::default(),
+    };
+    logging::init(&config.log)?;
+
+    let command = match opt.command {
+        Some(Command::Transcode { source, url, file }) => {
+            return transcode(source, url, file).await;
+        }
+        command => command,
+    };
+
+    let tape = match (opt.record, opt.replay) {
+        (Some(dir), _) => {
+            fs::create_dir_all(&dir)?;
+            Some(Tape::Record(dir))
+        }
+        (None, Some(dir)) => Some(Tape::Replay(dir)),
+        (None, None) => None,
+    };
+    let state = Arc::new(State::new(config, tape)?);
+
+    if let Some(Command::Build { out }) = command {
+        return build::run(&state, &out).await;
+    }
+
+    scheduler::spawn(&state)?;
+
+    let addr = (&*opt.host, opt.port)
+        .to_socket_addrs()
+        .unwrap()
+        .next()
+        .unwrap();
+
+    let new_service = make_service_fn(|_| {
+        let state = state.clone();
+        let service = service_fn(move |request| router::route(request, state.clone()));
+        async { Ok::<_, Infallible>(service) }
+    });
+    let (stop, stopped) = oneshot::channel::<()>();
+    let server = Server::bind(&addr)
+        .serve(new_service)
+        .with_graceful_shutdown(async {
+            let _ = stopped.await;
+        });
+    tokio::pin!(server);
+
+    if let Either::Left((result, _)) =
+        future::select(&mut server, Box::pin(shutdown::signal())).await
+    {
+        return Ok(result?);
+    }
+
+    // Stop accepting connections and let the open ones and the background tasks finish.
+    let timeout = state.config.shutdown_timeout;
+    info!(timeout_secs = timeout.as_secs_f64(), "shutting down");
+    let _ = stop.send(());
+    let deadline = Instant::now() + timeout;
+    match time::timeout_at(deadline, server).await {
+        Ok(result) => result?,
+        Err(_) => warn!("closing the connections still open at the deadline"),
+    }
+    let tasks = shutdown::drain(deadline).await;
+    if tasks > 0 {
+        warn!(
+            tasks,
+            "abandoning the background tasks still running at the deadline"
+        );
+    }
+    if let Err(e) = state.flush().await {
+        error!(error = %e, "failed to save the state");
+    }
+    info!("shut down");
+
+    Ok(())
+}
+
+async fn transcode(source: Source, url: Option<Url>, file: Option<PathBuf>) -> anyhow::Result<()> {
+    let url = match url {
+        Some(url) => {
+            if Source::from_url(&url) != Some(source) {
+                anyhow::bail!("`{}` is not a feed of {}", url, source.name());
+            }
+            url
+        }
+        None => match source.default_url() {
+            Some(url) => url.parse()?,
+            None => anyhow::bail!("{} requires `--url`", source.name()),
+        },
+    };
+
+    let input = match file {
+        Some(ref path) => fs::read(path)?,
+        None => {
+            let mut buf = Vec::new();
+            io::stdin().read_to_end(&mut buf)?;
+            buf
+        }
+    };
+    let input = stream::iter(Some(Ok(Bytes::from(input))));
+
+    let atom = source.transcode_to_vec(url, input).await?;
+    io::stdout().write_all(&atom)?;
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! Set-up of the structured logs written to the standard error.
+
+use std::{env, io, panic};
+
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{Log, LogFormat};
+
+/// Header carrying the id of a request, which is also in the logs of the request.
+pub const REQUEST_ID: &str = "x-request-id";
+
+/// Installs the global logger. `RUST_LOG` overrides the configured level if it is set.
+pub fn init(config: &Log) -> anyhow::Result<()> {
+    let filter = match env::var("RUST_LOG") {
+        Ok(filter) if !filter.is_empty() => filter,
+        _ => config.level.clone(),
+    };
+    let filter = EnvFilter::try_new(&filter)
+        .map_err(|e| anyhow::anyhow!("invalid log level `{}`: {}", filter, e))?;
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr);
+    let result = match config.format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .try_init(),
+    };
+    result.map_err(|e| anyhow::anyhow!(e))?;
+
+    // Panics, e.g. in the background tasks, would otherwise bypass the structured logs.
+    panic::set_hook(Box::new(|info| tracing::error!(panic = %info, "panicked")));
+    Ok(())
+}
+
+/// Returns a new id for a request.
+pub fn request_id() -> String {
+    crate::util::hex(&rand::random::<[u8; 8]>())
+}
@@ -0,0 +1,250 @@
+//! Prometheus metrics of the feeder, served at `/metrics` in the text exposition format.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use hyper::{header::CONTENT_TYPE, Body, Response, StatusCode};
+
+use crate::transcode::Source;
+
+pub const PATH: &str = "/metrics";
+
+/// Upper bounds of the buckets of the duration histograms, in seconds.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    requests: BTreeMap::new(),
+    upstream_requests: BTreeMap::new(),
+    upstream_errors: BTreeMap::new(),
+    transcodes: BTreeMap::new(),
+    entries: BTreeMap::new(),
+    cache: BTreeMap::new(),
+});
+
+static BLOCKING_TASKS: AtomicUsize = AtomicUsize::new(0);
+
+struct Registry {
+    /// Requests served, by route and status code.
+    requests: BTreeMap<(&'static str, u16), u64>,
+    /// Durations of the requests to the upstreams, by source.
+    upstream_requests: BTreeMap<&'static str, Histogram>,
+    /// Failed requests to the upstreams, by source and kind of failure.
+    upstream_errors: BTreeMap<(&'static str, &'static str), u64>,
+    /// Durations of the transcodings, by source.
+    transcodes: BTreeMap<&'static str, Histogram>,
+    /// Entries in the produced feeds, by source.
+    entries: BTreeMap<&'static str, u64>,
+    /// Lookups of feed requests in the cache, by result.
+    cache: BTreeMap<&'static str, u64>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Number of observations in each bucket, not cumulative.
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+/// Counts a blocking task as in flight until it is dropped.
+pub struct BlockingTask(());
+
+/// Counts a request served on `route` with `status`.
+pub fn request(route: &'static str, status: StatusCode) {
+    *registry()
+        .requests
+        .entry((route, status.as_u16()))
+        .or_default() += 1;
+}
+
+/// Records the duration of an attempt of a request to the upstream of `source`.
+pub fn upstream_request(source: Source, elapsed: Duration) {
+    registry()
+        .upstream_requests
+        .entry(source.name())
+        .or_default()
+        .observe(elapsed);
+}
+
+/// Counts a failed request to the upstream of `source`, e.g. of the kind `"timeout"`.
+pub fn upstream_error(source: Source, kind: &'static str) {
+    *registry()
+        .upstream_errors
+        .entry((source.name(), kind))
+        .or_default() += 1;
+}
+
+/// Records the duration of a transcoding, including the time spent reading the upstream body.
+pub fn transcode(source: Source, elapsed: Duration) {
+    registry()
+        .transcodes
+        .entry(source.name())
+        .or_default()
+        .observe(elapsed);
+}
+
+/// Counts the entries of a feed produced for `source`.
+pub fn entries(source: Source, count: usize) {
+    *registry().entries.entry(source.name()).or_default() += count as u64;
+}
+
+/// Counts a lookup of a feed request in the cache: `"hit"`, `"miss"` or `"stale"`.
+pub fn cache(result: &'static str) {
+    *registry().cache.entry(result).or_default() += 1;
+}
+
+pub fn blocking_task() -> BlockingTask {
+    BLOCKING_TASKS.fetch_add(1, Ordering::Relaxed);
+    BlockingTask(())
+}
+
+pub fn handle() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")
+        .body(Body::from(render()))
+        .unwrap()
+}
+
+fn render() -> String {
+    let registry = registry();
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "kf_feeder_http_requests_total",
+        "counter",
+        "Requests served, by route and status code.",
+    );
+    for (&(route, status), &count) in &registry.requests {
+        let labels = format!("route=\"{}\",status=\"{}\"", route, status);
+        sample(&mut out, "kf_feeder_http_requests_total", &labels, count);
+    }
+
+    header(
+        &mut out,
+        "kf_feeder_upstream_request_duration_seconds",
+        "histogram",
+        "Durations of the requests to the upstreams, including retries as separate requests.",
+    );
+    for (&source, histogram) in &registry.upstream_requests {
+        let labels = format!("source=\"{}\"", source);
+        histogram.render(
+            &mut out,
+            "kf_feeder_upstream_request_duration_seconds",
+            &labels,
+        );
+    }
+
+    header(
+        &mut out,
+        "kf_feeder_upstream_errors_total",
+        "counter",
+        "Failed requests to the upstreams, by kind of failure.",
+    );
+    for (&(source, kind), &count) in &registry.upstream_errors {
+        let labels = format!("source=\"{}\",kind=\"{}\"", source, kind);
+        sample(&mut out, "kf_feeder_upstream_errors_total", &labels, count);
+    }
+
+    header(
+        &mut out,
+        "kf_feeder_transcode_duration_seconds",
+        "histogram",
+        "Durations of the transcodings, including reading the upstream bodies.",
+    );
+    for (&source, histogram) in &registry.transcodes {
+        let labels = format!("source=\"{}\"", source);
+        histogram.render(&mut out, "kf_feeder_transcode_duration_seconds", &labels);
+    }
+
+    header(
+        &mut out,
+        "kf_feeder_entries_total",
+        "counter",
+        "Entries in the produced feeds.",
+    );
+    for (&source, &count) in &registry.entries {
+        let labels = format!("source=\"{}\"", source);
+        sample(&mut out, "kf_feeder_entries_total", &labels, count);
+    }
+
+    header(
+        &mut out,
+        "kf_feeder_cache_lookups_total",
+        "counter",
+        "Lookups of feed requests in the cache, by result.",
+    );
+    for (&result, &count) in &registry.cache {
+        let labels = format!("result=\"{}\"", result);
+        sample(&mut out, "kf_feeder_cache_lookups_total", &labels, count);
+    }
+
+    header(
+        &mut out,
+        "kf_feeder_blocking_tasks",
+        "gauge",
+        "Blocking tasks in flight, such as transcodings and file writes.",
+    );
+    let tasks = BLOCKING_TASKS.load(Ordering::Relaxed);
+    sample(&mut out, "kf_feeder_blocking_tasks", "", tasks as u64);
+
+    out
+}
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    // The registry holds plain numbers, which stay consistent even if a holder panicked.
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn sample<T: std::fmt::Display>(out: &mut String, name: &str, labels: &str, value: T) {
+    if labels.is_empty() {
+        writeln!(out, "{} {}", name, value).unwrap();
+    } else {
+        writeln!(out, "{}{{{}}} {}", name, labels, value).unwrap();
+    }
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|&bound| secs <= bound) {
+            self.buckets[i] += 1;
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let bucket = format!("{}_bucket", name);
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(&self.buckets) {
+            cumulative += count;
+            let labels = format!("{},le=\"{}\"", labels, bound);
+            sample(out, &bucket, &labels, cumulative);
+        }
+        sample(out, &bucket, &format!("{},le=\"+Inf\"", labels), self.count);
+        sample(out, &format!("{}_sum", name), labels, self.sum);
+        sample(out, &format!("{}_count", name), labels, self.count);
+    }
+}
+
+impl Drop for BlockingTask {
+    fn drop(&mut self) {
+        BLOCKING_TASKS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -1,122 +1,432 @@
-use std::{fmt::Display, mem};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use futures::TryFutureExt;
+use bytes::Bytes;
+use futures::{future, StreamExt};
 use hyper::{
-    header::{HeaderValue, ACCEPT_ENCODING, CONNECTION, CONTENT_LENGTH, CONTENT_TYPE, HOST, RANGE},
-    Body, Request, Response, StatusCode,
+    body::Sender,
+    header::{
+        HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_LENGTH,
+        CONTENT_TYPE, ETAG, IF_NONE_MATCH, LAST_MODIFIED, LINK, ORIGIN, RETRY_AFTER, WARNING,
+    },
+    http::request::Parts,
+    Body, Method, Request, Response, StatusCode,
+};
+use reqwest::{Request as Reqwest, Response as Reswponse};
+use tracing::{error, info, warn, Instrument};
+
+use crate::{
+    cache::{Cache, Entry},
+    compress::{self, Encoding},
+    config::{Config, Validate},
+    cors,
+    feed::Feed,
+    health::{self, Polls},
+    history::History,
+    logging, metrics,
+    status::{self, Status},
+    tape::Tape,
+    transcode::Source,
+    upstream::{self, Upstream},
+    util, validate,
+    webhook::Webhooks,
+    websub::{self, Hub},
 };
-use reqwest::{Client, Request as Reqwest, Response as Reswponse};
 
-use crate::transcode::{self, Transcode};
+#[cfg(test)]
+mod tests;
+
+pub struct State {
+    pub config: Config,
+    pub upstreams: HashMap<Source, Upstream>,
+    pub cache: Cache,
+    pub history: History,
+    pub hub: Hub,
+    pub webhooks: Webhooks,
+    pub tape: Option<Arc<Tape>>,
+    pub polls: Polls,
+    pub status: Arc<Status>,
+}
+
+impl State {
+    pub fn new(config: Config, tape: Option<Tape>) -> anyhow::Result<Self> {
+        let tape = tape.map(Arc::new);
+        let status = Arc::new(Status::default());
+        let upstreams = Source::ALL
+            .iter()
+            .map(|&source| {
+                let upstream = Upstream::new(
+                    source,
+                    config.sources.get(source),
+                    &config.origins,
+                    &config.user_agent,
+                    tape.clone(),
+                    status.clone(),
+                )?;
+                Ok((source, upstream))
+            })
+            .collect::<reqwest::Result<_>>()?;
+        let history = History::new(&config)?;
+        let hub = Hub::new(&config)?;
+        let webhooks = Webhooks::new(&config)?;
+        Ok(State {
+            config,
+            upstreams,
+            cache: Cache::default(),
+            history,
+            hub,
+            webhooks,
+            tape,
+            polls: Polls::default(),
+            status,
+        })
+    }
+
+    /// Records, validates and parses the Atom document transcoded for `url`, counting its entries
+    /// in the metrics and on the status page.
+    pub fn produce(&self, source: Source, url: &url::Url, atom: &Bytes) -> anyhow::Result<Feed> {
+        if let Some(ref tape) = self.tape {
+            tape.record_feed(url, atom.clone());
+        }
+        validate::check(source, self.config.sources.get(source).validate, url, atom)?;
+        let feed = Feed::parse(atom)?;
+        metrics::entries(source, feed.entries.len());
+        self.status.produced(source, feed.entries.len());
+        Ok(feed)
+    }
+
+    /// Saves the persistent state, including changes that are not saved right away, such as
+    /// the expiry of WebSub subscriptions.
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        self.history.save().await?;
+        self.webhooks.save().await?;
+        self.hub.save().await
+    }
+}
 
-pub async fn route(request: Request<Body>, client: Client) -> anyhow::Result<Response<Body>> {
+pub async fn route(request: Request<Body>, state: Arc<State>) -> anyhow::Result<Response<Body>> {
+    let id = logging::request_id();
+    let span = tracing::info_span!(
+        "request",
+        id = %id,
+        method = %request.method(),
+        path = %request.uri(),
+    );
+    let start = Instant::now();
+    let result = serve(request, state).instrument(span.clone()).await;
+    let _enter = span.enter();
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    match result {
+        Ok((route, mut res)) => {
+            info!(route, status = res.status().as_u16(), elapsed_ms, "served");
+            let id = HeaderValue::from_str(&id).unwrap();
+            res.headers_mut().insert(logging::REQUEST_ID, id);
+            Ok(res)
+        }
+        Err(e) => {
+            error!(error = %e, elapsed_ms, "failed to serve");
+            Err(e)
+        }
+    }
+}
+
+async fn serve(
+    request: Request<Body>,
+    state: Arc<State>,
+) -> anyhow::Result<(&'static str, Response<Body>)> {
+    let origin = request.headers().get(ORIGIN).cloned();
+    let encoding = Encoding::negotiate(request.headers().get(ACCEPT_ENCODING));
+    let (route, mut res) = match *request.method() {
+        Method::OPTIONS if !state.config.cors.allow_origins.is_empty() => {
+            ("preflight", cors::preflight(&state.config.cors))
+        }
+        _ if state.config.websub.enabled && request.uri().path() == websub::PATH => {
+            ("websub", websub::handle(request, state.clone()).await?)
+        }
+        Method::GET if state.config.metrics && request.uri().path() == metrics::PATH => {
+            ("metrics", metrics::handle())
+        }
+        Method::GET if state.config.status && request.uri().path() == status::PATH => {
+            ("status", status::handle(&state))
+        }
+        Method::GET | Method::HEAD if request.uri().path() == health::LIVE_PATH => {
+            ("healthz", health::live())
+        }
+        Method::GET | Method::HEAD if request.uri().path() == health::READY_PATH => {
+            ("readyz", health::ready(&state))
+        }
+        _ => dispatch(request, &state).await?,
+    };
+    metrics::request(route, res.status());
+    compress::apply(encoding, &mut res);
+    cors::apply(&state.config.cors, origin.as_ref(), &mut res);
+    Ok((route, res))
+}
+
+/// Serves a feed request, returning the name of its source as the route.
+async fn dispatch(
+    request: Request<Body>,
+    state: &Arc<State>,
+) -> anyhow::Result<(&'static str, Response<Body>)> {
     let not_found = || {
         let body = "Not found";
-        Ok(Response::builder()
+        let res = Response::builder()
             .header(CONTENT_LENGTH, body.len() as u64)
             .status(StatusCode::NOT_FOUND)
             .body(Body::from(body))
-            .unwrap())
+            .unwrap();
+        Ok(("not-found", res))
     };
 
-    let mut parts = request.into_parts().0;
+    let parts = request.into_parts().0;
 
     let head = match parts.method {
-        hyper::Method::HEAD => true,
-        hyper::Method::GET => false,
+        Method::HEAD => true,
+        Method::GET => false,
         _ => return not_found(),
     };
 
     let url: url::Url = match parts.uri.path_and_query() {
         None => return not_found(),
-        Some(ref paq) if !paq.as_str().starts_with('/') => return not_found(),
+        Some(paq) if !paq.as_str().starts_with('/') => return not_found(),
         Some(paq) => match paq.as_str()[1..].parse() {
             Ok(url) => url,
             Err(_) => return not_found(),
         },
     };
 
-    parts.headers.remove(HOST);
-    parts.headers.remove(RANGE);
-    parts.headers.remove(ACCEPT_ENCODING);
-    let reqwest = |url| {
-        let mut reqwest = Reqwest::new(parts.method, url);
-        *reqwest.headers_mut() = parts.headers;
-        client.execute(reqwest)
+    let source = match Source::from_url(&url) {
+        Some(source) => source,
+        None => return not_found(),
     };
 
-    let path = &url[..url::Position::AfterPath];
+    let link = websub::link(&state.config, &url);
+    let mut res = fetch(state, &parts, url, source, head).await?;
+    if let Some(link) = link {
+        res.headers_mut().insert(LINK, link);
+    }
+    Ok((source.name(), res))
+}
 
-    match path {
-        "https://kemono-friends.sega.jp/news/articles.json" => {
-            let resw = reqwest(url).await?;
-            return proxy_response(transcode::kemono_friends_sega_jp::Transcode, resw, head);
-        }
-        "https://www.kadokawa.co.jp/json.jsp" => {
-            if let Some(q) = url.query() {
-                for pair in q.split('&') {
-                    if pair.starts_with("id=") && pair[3..] == *"342" {
-                        let resw = reqwest(url).await?;
-                        return proxy_response(transcode::kadokawa_co_jp::Transcode, resw, head);
-                    }
-                }
+async fn fetch(
+    state: &Arc<State>,
+    parts: &Parts,
+    url: url::Url,
+    source: Source,
+    head: bool,
+) -> anyhow::Result<Response<Body>> {
+    let if_none_match = parts.headers.get(IF_NONE_MATCH);
+
+    // Feeds polled in the background are served from the cache as long as the poller keeps up.
+    if let Some(feed) = state.config.feed(&url) {
+        if let Some(entry) = state.cache.get(url.as_str()) {
+            // The next poll may take up to the upstream timeout to replace the entry.
+            let fresh = state.config.poll_interval(feed) + state.config.sources.get(source).timeout;
+            if entry.age() <= fresh {
+                metrics::cache("hit");
+                return Ok(entry.response(head, if_none_match));
+            }
+            if let Some(res) = stale(state, source, &url, head, if_none_match) {
+                return Ok(res);
             }
         }
-        _ => {}
     }
+    metrics::cache("miss");
 
-    const PREFIX: &str = "https://www.jvcmusic.co.jp/-/News/A";
-    if path.starts_with(PREFIX)
-        && path.as_bytes()[PREFIX.len()..][..6]
-            .iter()
-            .all(|c| c.is_ascii_digit())
-        && path[(PREFIX.len() + 6)..] == *".json"
-    {
-        let resw = reqwest(url).await?;
-        return proxy_response(transcode::jvcmusic_co_jp::Transcode, resw, head);
+    let mut reqwest = Reqwest::new(parts.method.clone(), url.clone());
+    let headers = reqwest.headers_mut();
+    for name in &state.config.forward_headers {
+        for value in parts.headers.get_all(name) {
+            headers.append(name.clone(), value.clone());
+        }
     }
 
-    not_found()
+    let resw = match state.upstreams[&source].execute(reqwest).await {
+        Ok(resw) if resw.status().is_server_error() => {
+            let status = resw.status().as_u16();
+            warn!(source = source.name(), %url, status, "upstream server error");
+            let error = format!("upstream responded with {}", resw.status());
+            state.status.failed(source, &error);
+            if let Some(res) = stale(state, source, &url, head, if_none_match) {
+                return Ok(res);
+            }
+            resw
+        }
+        Ok(resw) => resw,
+        Err(e) => {
+            warn!(source = source.name(), %url, error = %e, "upstream request failed");
+            state.status.failed(source, &e);
+            if let Some(res) = stale(state, source, &url, head, if_none_match) {
+                return Ok(res);
+            }
+            let mut res = Response::builder();
+            if let upstream::Error::CircuitOpen(retry_after) = e {
+                let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                res = res
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .header(RETRY_AFTER, secs);
+            } else {
+                res = res.status(StatusCode::BAD_GATEWAY);
+            }
+            return Ok(res.body(Body::default())?);
+        }
+    };
+
+    proxy_response(state, source, url, resw, head).await
 }
 
-fn proxy_response<T>(
-    transcode: T,
-    mut resw: Reswponse,
+/// Returns the last good feed for `url` if it is still within its cache lifetime.
+fn stale(
+    state: &State,
+    source: Source,
+    url: &url::Url,
     head: bool,
-) -> anyhow::Result<Response<Body>>
-where
-    T: Transcode + Send + Sync + 'static,
-    T::Future: Send + 'static,
-    T::Error: Display,
-{
+    if_none_match: Option<&HeaderValue>,
+) -> Option<Response<Body>> {
+    let entry = state.cache.get(url.as_str())?;
+    let age = entry.age();
+    if age > state.config.cache_lifetime(source, url) {
+        return None;
+    }
+
+    let age_secs = age.as_secs();
+    warn!(source = source.name(), %url, age_secs, "serving stale feed");
+    metrics::cache("stale");
+    let mut res = entry.response(head, if_none_match);
+    let warning = r#"111 kf-feeder "Revalidation Failed""#;
+    res.headers_mut()
+        .insert(WARNING, HeaderValue::from_static(warning));
+    Some(res)
+}
+
+/// Upstream response headers that are passed through to the client.
+const FORWARDED_HEADERS: [HeaderName; 1] = [LAST_MODIFIED];
+
+/// Returns the headers of a feed response with the given time to live.
+pub fn feed_headers(ttl: Duration) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let content_type = "application/atom+xml;charset=UTF-8";
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    let cache_control = format!("public, max-age={}", ttl.as_secs());
+    headers.insert(
+        CACHE_CONTROL,
+        HeaderValue::from_str(&cache_control).unwrap(),
+    );
+    headers
+}
+
+async fn proxy_response(
+    state: &Arc<State>,
+    source: Source,
+    url: url::Url,
+    resw: Reswponse,
+    head: bool,
+) -> anyhow::Result<Response<Body>> {
     let mut res = Response::builder();
     let headers = res.headers_mut().unwrap();
-    mem::swap(headers, resw.headers_mut());
+    for name in &FORWARDED_HEADERS {
+        if let Some(value) = resw.headers().get(name) {
+            headers.insert(name, value.clone());
+        }
+    }
+    // The feed is a different representation of the upstream document, so its validator is only
+    // weakly equivalent to the upstream one.
+    if let Some(etag) = resw.headers().get(ETAG) {
+        headers.insert(ETAG, util::weak_etag(etag));
+    }
 
     match resw.status() {
-        StatusCode::OK => {
+        s @ StatusCode::OK | s @ StatusCode::NOT_MODIFIED => {
+            headers.extend(feed_headers(state.config.sources.get(source).ttl));
+            if s == StatusCode::NOT_MODIFIED {
+                headers.remove(CONTENT_TYPE);
+                return Ok(res.status(s).body(Body::default())?);
+            }
+
+            let validate = state.config.sources.get(source).validate;
             let body = if head {
                 Body::default()
+            } else if validate == Validate::Reject {
+                // The whole feed has to be checked before any of it is sent.
+                let headers = headers.clone();
+                let result = async {
+                    let atom = source
+                        .transcode_to_vec(url.clone(), resw.bytes_stream())
+                        .await?;
+                    let atom = Bytes::from(atom);
+                    store(state, source, url.clone(), headers, atom.clone())?;
+                    Ok::<_, anyhow::Error>(atom)
+                };
+                match result.await {
+                    Ok(atom) => Body::from(atom),
+                    Err(e) => {
+                        error!(source = source.name(), %url, error = %e, "transcoding failed");
+                        state.status.failed(source, &e);
+                        return Ok(Response::builder()
+                            .status(StatusCode::BAD_GATEWAY)
+                            .header(CACHE_CONTROL, "no-store")
+                            .body(Body::default())?);
+                    }
+                }
             } else {
                 let (tx, body) = Body::channel();
-                let task = transcode
-                    .transcode(resw.url().clone(), resw.bytes_stream(), tx)
-                    .map_err(eprintln);
-                tokio::spawn(task);
+                let (inner_tx, inner) = Body::channel();
+                let task = source.transcode(url.clone(), resw.bytes_stream(), inner_tx);
+                let headers = headers.clone();
+                let state = state.clone();
+                let task = async move {
+                    let result = match future::join(task, tee(inner, tx)).await {
+                        (Ok(()), buf) => store(&state, source, url.clone(), headers, buf.into()),
+                        (Err(e), _) => Err(e.into()),
+                    };
+                    if let Err(e) = result {
+                        error!(source = source.name(), %url, error = %e, "transcoding failed");
+                        state.status.failed(source, &e);
+                    }
+                };
+                util::spawn(task.in_current_span());
                 body
             };
 
-            headers.remove(CONNECTION);
-            headers.remove(CONTENT_LENGTH);
-            let content_type = "application/atom+xml;charset=UTF-8";
-            headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
-
             Ok(res.body(body)?)
         }
-        s => Ok(res.status(s).body(Body::default())?),
+        s => {
+            headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+            Ok(res.status(s).body(Body::default())?)
+        }
     }
 }
 
-fn eprintln<T: Display>(t: T) {
-    eprintln!("{}", t);
+/// Validates a feed transcoded for a client and puts it into the cache.
+fn store(
+    state: &State,
+    source: Source,
+    url: url::Url,
+    headers: HeaderMap,
+    atom: Bytes,
+) -> anyhow::Result<()> {
+    let feed = state.produce(source, &url, &atom)?;
+    let lifetime = state.config.cache_lifetime(source, &url);
+    state
+        .cache
+        .insert(url.into(), Entry::new(headers, atom, feed), lifetime);
+    Ok(())
+}
+
+/// Forwards `body` to `tx` and returns the whole body, even if the client has gone away.
+async fn tee(mut body: Body, tx: Sender) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut tx = Some(tx);
+    while let Some(Ok(chunk)) = body.next().await {
+        buf.extend_from_slice(&chunk);
+        if let Some(ref mut sender) = tx {
+            if sender.send_data(chunk).await.is_err() {
+                tx = None;
+            }
+        }
+    }
+    buf
 }
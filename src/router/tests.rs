@@ -0,0 +1,756 @@
+//! End-to-end tests of the router against a mock upstream.
+
+use std::{
+    convert::Infallible,
+    env, fs,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use bytes::Bytes;
+use hyper::{
+    header::{
+        HeaderMap, HeaderValue, ACCEPT_LANGUAGE, ACCESS_CONTROL_ALLOW_METHODS,
+        ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, AGE, CACHE_CONTROL,
+        CONTENT_TYPE, COOKIE, ETAG, IF_NONE_MATCH, ORIGIN, RETRY_AFTER, USER_AGENT, VARY, WARNING,
+    },
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use reqwest::Client;
+
+use super::{route, State};
+use crate::{
+    cache::Entry,
+    config::{self, Config, Validate},
+    feed::Feed,
+    scheduler,
+    tape::Tape,
+};
+
+const ETAG_VALUE: &str = "\"v1\"";
+/// The validator of the feeds transcoded from the upstream documents tagged with `ETAG_VALUE`.
+const WEAK_ETAG_VALUE: &str = "W/\"v1\"";
+
+/// A local upstream that serves the transcoder fixtures and records the requests it receives.
+struct Mock {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Request<()>>>>,
+    /// Makes every response a `500 Internal Server Error` while set.
+    failing: Arc<AtomicBool>,
+}
+
+/// The feeder under test, fetching from a `Mock`.
+struct Feeder {
+    addr: SocketAddr,
+    mock: Mock,
+    client: Client,
+    state: Arc<State>,
+}
+
+impl Mock {
+    fn start() -> Self {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let failing = Arc::new(AtomicBool::new(false));
+        let (log, fail) = (requests.clone(), failing.clone());
+        let make_service = make_service_fn(move |_| {
+            let (log, fail) = (log.clone(), fail.clone());
+            let service = service_fn(move |request: Request<Body>| {
+                let (parts, _) = request.into_parts();
+                let res = if fail.load(Ordering::SeqCst) {
+                    respond("/-/News/A000500.json", &parts.headers)
+                } else {
+                    respond(parts.uri.path(), &parts.headers)
+                };
+                log.lock().unwrap().push(Request::from_parts(parts, ()));
+                async { Ok::<_, Infallible>(res) }
+            });
+            async { Ok::<_, Infallible>(service) }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        Mock {
+            addr,
+            requests,
+            failing,
+        }
+    }
+
+    fn last_request(&self) -> Request<()> {
+        self.requests
+            .lock()
+            .unwrap()
+            .pop()
+            .expect("no upstream request")
+    }
+}
+
+fn respond(path: &str, headers: &HeaderMap) -> Response<Body> {
+    let fixture = |name: &str| {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name);
+        Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .header(ETAG, ETAG_VALUE)
+            .body(Body::from(fs::read(path).unwrap()))
+            .unwrap()
+    };
+    let status = |status: StatusCode| {
+        Response::builder()
+            .status(status)
+            .body(Body::default())
+            .unwrap()
+    };
+
+    // `If-None-Match` uses the weak comparison.
+    let if_none_match = headers.get(IF_NONE_MATCH).map(|v| v.as_bytes());
+    let if_none_match = if_none_match.map(|v| v.strip_prefix(b"W/").unwrap_or(v));
+    match path {
+        _ if if_none_match == Some(ETAG_VALUE.as_bytes()) => status(StatusCode::NOT_MODIFIED),
+        "/kemono-friends/news/articles.json" => fixture("kemono-friends-sega-jp/articles.json"),
+        "/json.jsp" => fixture("kadokawa-co-jp/search.json"),
+        "/-/News/A025287.json" => fixture("jvcmusic-co-jp/news.json"),
+        // An article without `open_dt` makes an entry without `atom:updated`.
+        "/-/News/A000422.json" => Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"title":"t","url":"https://www.jvcmusic.co.jp/-/Artist/A000422.html","contents":{"articles":[{"url":"https://www.jvcmusic.co.jp/-/News/A000422/1.html","title":"t"}]}}"#,
+            ))
+            .unwrap(),
+        "/-/News/A000404.json" => status(StatusCode::NOT_FOUND),
+        "/-/News/A000500.json" => status(StatusCode::INTERNAL_SERVER_ERROR),
+        _ => status(StatusCode::NOT_FOUND),
+    }
+}
+
+impl Feeder {
+    fn start() -> Self {
+        Feeder::start_with(None, |_| {})
+    }
+
+    /// Starts a feeder with a tape and a configuration adjusted by `configure`.
+    fn start_with(tape: Option<Tape>, configure: impl FnOnce(&mut Config)) -> Self {
+        let mock = Mock::start();
+        let config = format!(
+            r#"
+            user-agent = "kf-feeder-test"
+
+            [origins]
+            "https://kemono-friends.sega.jp" = "http://{0}/kemono-friends/"
+            "https://www.kadokawa.co.jp" = "http://{0}/"
+            "https://www.jvcmusic.co.jp" = "http://{0}/"
+
+            [sources.kemono-friends-sega-jp]
+            retries = 0
+
+            [sources.kadokawa-co-jp]
+            retries = 0
+
+            [sources.jvcmusic-co-jp]
+            retries = 0
+            "#,
+            mock.addr,
+        );
+        let mut config: Config = toml::from_str(&config).unwrap();
+        configure(&mut config);
+        let state = Arc::new(State::new(config, tape).unwrap());
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            let service = service_fn(move |request| route(request, state.clone()));
+            async { Ok::<_, Infallible>(service) }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        // Compression is left out so that the bodies and validators are the uncompressed ones.
+        let client = Client::builder().gzip(false).brotli(false).build().unwrap();
+        Feeder {
+            addr,
+            mock,
+            client,
+            state,
+        }
+    }
+
+    fn url(&self, upstream: &str) -> String {
+        format!("http://{}/{}", self.addr, upstream)
+    }
+}
+
+fn golden(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    fs::read_to_string(path).unwrap()
+}
+
+#[tokio::test]
+async fn transcodes_each_source() {
+    let feeder = Feeder::start();
+    let cases = [
+        (
+            "https://kemono-friends.sega.jp/news/articles.json",
+            "/kemono-friends/news/articles.json",
+            "kemono-friends-sega-jp/articles.atom",
+        ),
+        (
+            "https://www.kadokawa.co.jp/json.jsp?id=342&category=comic&sort=new",
+            "/json.jsp?id=342&category=comic&sort=new",
+            "kadokawa-co-jp/search.atom",
+        ),
+        (
+            "https://www.jvcmusic.co.jp/-/News/A025287.json",
+            "/-/News/A025287.json",
+            "jvcmusic-co-jp/news.atom",
+        ),
+    ];
+
+    for &(upstream, path, golden_name) in &cases {
+        let res = feeder
+            .client
+            .get(&feeder.url(upstream))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{}", upstream);
+        assert_eq!(
+            res.headers()[CONTENT_TYPE],
+            "application/atom+xml;charset=UTF-8"
+        );
+        assert_eq!(res.headers()[CACHE_CONTROL], "public, max-age=600");
+        assert_eq!(res.headers()[ETAG], WEAK_ETAG_VALUE);
+        assert_eq!(res.text().await.unwrap(), golden(golden_name));
+
+        let request = feeder.mock.last_request();
+        assert_eq!(request.uri().path_and_query().unwrap().as_str(), path);
+    }
+}
+
+#[tokio::test]
+async fn forwards_allow_listed_headers() {
+    let feeder = Feeder::start();
+    let res = feeder
+        .client
+        .get(&feeder.url("https://kemono-friends.sega.jp/news/articles.json"))
+        .header(ACCEPT_LANGUAGE, "ja")
+        .header(COOKIE, "session=secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let request = feeder.mock.last_request();
+    let headers = request.headers();
+    assert_eq!(headers[ACCEPT_LANGUAGE], "ja");
+    assert_eq!(headers[USER_AGENT], "kf-feeder-test");
+    assert!(headers.get(COOKIE).is_none());
+}
+
+#[tokio::test]
+async fn passes_through_not_modified() {
+    let feeder = Feeder::start();
+    let res = feeder
+        .client
+        .get(&feeder.url("https://kemono-friends.sega.jp/news/articles.json"))
+        .header(IF_NONE_MATCH, HeaderValue::from_static(WEAK_ETAG_VALUE))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert!(res.headers().get(CONTENT_TYPE).is_none());
+    assert_eq!(res.headers()[CACHE_CONTROL], "public, max-age=600");
+    assert_eq!(res.headers()[VARY], "Accept-Encoding");
+}
+
+#[tokio::test]
+async fn head_has_no_body() {
+    let feeder = Feeder::start();
+    let res = feeder
+        .client
+        .head(&feeder.url("https://kemono-friends.sega.jp/news/articles.json"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers()[CONTENT_TYPE],
+        "application/atom+xml;charset=UTF-8"
+    );
+    assert_eq!(res.bytes().await.unwrap().len(), 0);
+    assert_eq!(*feeder.mock.last_request().method(), hyper::Method::HEAD);
+}
+
+#[tokio::test]
+async fn tags_responses_with_request_ids() {
+    let feeder = Feeder::start();
+    let mut ids = Vec::new();
+    for path in &[
+        "https://kemono-friends.sega.jp/news/articles.json",
+        "favicon.ico",
+    ] {
+        let res = feeder.client.get(&feeder.url(path)).send().await.unwrap();
+        let id = res.headers()["x-request-id"].to_str().unwrap().to_owned();
+        assert_eq!(id.len(), 16, "{}", id);
+        assert!(id.bytes().all(|b| b.is_ascii_hexdigit()), "{}", id);
+        ids.push(id);
+    }
+    assert_ne!(ids[0], ids[1]);
+
+    // Cross-origin readers can see the id too.
+    let feeder = Feeder::start_with(None, |config| {
+        config.cors.allow_origins = vec!["*".to_owned()];
+        config.cors.expose_headers = vec![ETAG];
+    });
+    let res = feeder
+        .client
+        .get(&feeder.url("https://kemono-friends.sega.jp/news/articles.json"))
+        .header(ORIGIN, "https://reader.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.headers()[ACCESS_CONTROL_EXPOSE_HEADERS],
+        "x-request-id, etag"
+    );
+}
+
+#[tokio::test]
+async fn answers_preflights_only_with_cors() {
+    let url = "https://kemono-friends.sega.jp/news/articles.json";
+    let feeder = Feeder::start();
+    let res = feeder
+        .client
+        .request(reqwest::Method::OPTIONS, &feeder.url(url))
+        .header(ORIGIN, "https://reader.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let feeder = Feeder::start_with(None, |config| {
+        config.cors.allow_origins = vec!["https://reader.example.com".to_owned()];
+    });
+    let res = feeder
+        .client
+        .request(reqwest::Method::OPTIONS, &feeder.url(url))
+        .header(ORIGIN, "https://reader.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_METHODS], "GET, HEAD");
+    assert_eq!(
+        res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+        "https://reader.example.com"
+    );
+}
+
+#[tokio::test]
+async fn passes_through_upstream_errors() {
+    let feeder = Feeder::start();
+    for &(code, status) in &[
+        ("A000404", StatusCode::NOT_FOUND),
+        ("A000500", StatusCode::INTERNAL_SERVER_ERROR),
+    ] {
+        let upstream = format!("https://www.jvcmusic.co.jp/-/News/{}.json", code);
+        let res = feeder
+            .client
+            .get(&feeder.url(&upstream))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status);
+        assert_eq!(res.headers()[CACHE_CONTROL], "no-store");
+        assert!(res.headers().get(CONTENT_TYPE).is_none());
+    }
+}
+
+#[tokio::test]
+async fn rejects_requests_while_the_circuit_is_open() {
+    let feeder = Feeder::start_with(None, |config| {
+        let source = &mut config.sources.jvcmusic_co_jp;
+        source.retries = 0;
+        source.failure_threshold = 1;
+        source.open_duration = Duration::from_secs(60);
+    });
+    let url = feeder.url("https://www.jvcmusic.co.jp/-/News/A000500.json");
+    let res = feeder.client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let requests = feeder.mock.requests.lock().unwrap().len();
+
+    let res = feeder.client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.headers()[RETRY_AFTER], "60");
+    assert_eq!(feeder.mock.requests.lock().unwrap().len(), requests);
+}
+
+#[tokio::test]
+async fn rejects_unknown_routes() {
+    let feeder = Feeder::start();
+    for path in &[
+        "",
+        "favicon.ico",
+        "https://example.com/feed.json",
+        "https://www.kadokawa.co.jp/json.jsp?id=1",
+        "https://www.jvcmusic.co.jp/-/News/A12",
+        "https://www.jvcmusic.co.jp/-/News/A1234567.json",
+    ] {
+        let res = feeder.client.get(&feeder.url(path)).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND, "{}", path);
+    }
+    let res = feeder
+        .client
+        .post(&feeder.url("https://kemono-friends.sega.jp/news/articles.json"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(feeder.mock.requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn rejects_invalid_feeds() {
+    let upstream = "https://www.jvcmusic.co.jp/-/News/A000422.json";
+
+    let feeder = Feeder::start_with(None, |config| {
+        config.sources.jvcmusic_co_jp.validate = Validate::Log;
+    });
+    let res = feeder
+        .client
+        .get(&feeder.url(upstream))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let feeder = Feeder::start_with(None, |config| {
+        config.sources.kemono_friends_sega_jp.validate = Validate::Reject;
+        config.sources.jvcmusic_co_jp.validate = Validate::Reject;
+    });
+    let res = feeder
+        .client
+        .get(&feeder.url("https://kemono-friends.sega.jp/news/articles.json"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = feeder
+        .client
+        .get(&feeder.url(upstream))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+    assert_eq!(res.headers()[CACHE_CONTROL], "no-store");
+}
+
+#[tokio::test]
+async fn records_and_replays() {
+    let dir = env::temp_dir().join(format!("kf-feeder-tape-{:016x}", rand::random::<u64>()));
+    fs::create_dir_all(&dir).unwrap();
+    let upstream = "https://kemono-friends.sega.jp/news/articles.json";
+
+    let feeder = Feeder::start_with(Some(Tape::Record(dir.clone())), |_| {});
+    let recorded = feeder
+        .client
+        .get(&feeder.url(upstream))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(recorded.status(), StatusCode::OK);
+    let recorded = recorded.text().await.unwrap();
+
+    let feeder = Feeder::start_with(Some(Tape::Replay(dir.clone())), |_| {});
+    let replayed = feeder
+        .client
+        .get(&feeder.url(upstream))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(replayed.status(), StatusCode::OK);
+    assert_eq!(replayed.headers()[ETAG], WEAK_ETAG_VALUE);
+    assert_eq!(replayed.text().await.unwrap(), recorded);
+    assert!(feeder.mock.requests.lock().unwrap().is_empty());
+
+    // The feed is recorded in the background.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let atom = loop {
+        let atom = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "atom"));
+        if let Some(atom) = atom {
+            break atom;
+        }
+        assert!(Instant::now() < deadline, "the feed was not recorded");
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+    };
+    assert_eq!(fs::read_to_string(atom).unwrap(), recorded);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn serves_metrics() {
+    let feeder = Feeder::start();
+    let res = feeder
+        .client
+        .get(&feeder.url("metrics"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let feeder = Feeder::start_with(None, |config| config.metrics = true);
+    let upstream = "https://www.jvcmusic.co.jp/-/News/A025287.json";
+    let res = feeder
+        .client
+        .get(&feeder.url(upstream))
+        .send()
+        .await
+        .unwrap();
+    res.text().await.unwrap();
+
+    let res = feeder
+        .client
+        .get(&feeder.url("metrics"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers()[CONTENT_TYPE],
+        "text/plain; version=0.0.4; charset=utf-8"
+    );
+    // The metrics are global, so other tests may have added to them.
+    let metrics = res.text().await.unwrap();
+    for prefix in &[
+        r#"kf_feeder_http_requests_total{route="jvcmusic-co-jp",status="200"} "#,
+        r#"kf_feeder_http_requests_total{route="not-found",status="404"} "#,
+        r#"kf_feeder_upstream_request_duration_seconds_count{source="jvcmusic-co-jp"} "#,
+        r#"kf_feeder_cache_lookups_total{result="miss"} "#,
+        "kf_feeder_blocking_tasks ",
+    ] {
+        assert!(
+            metrics.lines().any(|line| line.starts_with(prefix)),
+            "no `{}` in\n{}",
+            prefix,
+            metrics
+        );
+    }
+}
+
+#[tokio::test]
+async fn reports_health_and_readiness() {
+    let get = |feeder: &Feeder, path: &'static str| {
+        let request = feeder.client.get(&feeder.url(path)).send();
+        async move {
+            let res = request.await.unwrap();
+            (res.status(), res.text().await.unwrap())
+        }
+    };
+    let feed = |url: &str| config::Feed {
+        url: url.parse().unwrap(),
+        name: None,
+        interval: None,
+    };
+
+    let feeder = Feeder::start_with(None, |config| {
+        config.feeds = vec![
+            feed("https://www.jvcmusic.co.jp/-/News/A025287.json"),
+            feed("https://www.jvcmusic.co.jp/-/News/A000500.json"),
+        ];
+    });
+    scheduler::spawn(&feeder.state).unwrap();
+    assert_eq!(
+        get(&feeder, "healthz").await,
+        (StatusCode::OK, "ok\n".to_owned())
+    );
+    for path in &["healthz", "readyz"] {
+        let res = feeder.client.head(&feeder.url(path)).send().await.unwrap();
+        assert_ne!(res.status(), StatusCode::NOT_FOUND, "{}", path);
+        assert_eq!(res.text().await.unwrap(), "", "{}", path);
+    }
+    let expected = "jvcmusic-co-jp https://www.jvcmusic.co.jp/-/News/A000500.json: \
+        never polled successfully (upstream responded with 500 Internal Server Error)\n";
+    let mut ready = get(&feeder, "readyz").await;
+    for _ in 0..50 {
+        if ready.1 == expected {
+            break;
+        }
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+        ready = get(&feeder, "readyz").await;
+    }
+    assert_eq!(
+        ready,
+        (StatusCode::SERVICE_UNAVAILABLE, expected.to_owned())
+    );
+
+    let feeder = Feeder::start_with(None, |config| {
+        config.feeds = vec![feed("https://www.jvcmusic.co.jp/-/News/A025287.json")];
+    });
+    scheduler::spawn(&feeder.state).unwrap();
+    let mut ready = get(&feeder, "readyz").await;
+    for _ in 0..50 {
+        if ready.0 == StatusCode::OK {
+            break;
+        }
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+        ready = get(&feeder, "readyz").await;
+    }
+    assert_eq!(ready, (StatusCode::OK, "ok\n".to_owned()));
+}
+
+#[tokio::test]
+async fn serves_status_page() {
+    let feeder = Feeder::start();
+    let res = feeder
+        .client
+        .get(&feeder.url("status"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let feeder = Feeder::start_with(None, |config| config.status = true);
+    for upstream in &[
+        "https://kemono-friends.sega.jp/news/articles.json",
+        "https://www.jvcmusic.co.jp/-/News/A000500.json",
+    ] {
+        let res = feeder
+            .client
+            .get(&feeder.url(upstream))
+            .send()
+            .await
+            .unwrap();
+        res.text().await.unwrap();
+    }
+
+    let row = |html: &str, source: &str| {
+        let prefix = format!("<tr><td>{}<td>", source);
+        let row = html.lines().find(|line| line.starts_with(&prefix)).unwrap();
+        row.split("<td>")
+            .skip(1)
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+
+    // Streamed feeds are counted once the client has received them.
+    let mut html = String::new();
+    for _ in 0..50 {
+        let res = feeder
+            .client
+            .get(&feeder.url("status"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/html; charset=utf-8");
+        html = res.text().await.unwrap();
+        if row(&html, "kemono-friends-sega-jp")[3] != "-" {
+            break;
+        }
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+    }
+
+    let kemono = row(&html, "kemono-friends-sega-jp");
+    assert_ne!(kemono[1], "-");
+    assert_eq!(kemono[2..4], ["200 OK", "3"]);
+    assert!(kemono[4].ends_with('s'), "{}", kemono[4]);
+    assert_eq!(kemono[5], "-");
+    let jvc = row(&html, "jvcmusic-co-jp");
+    assert_eq!(jvc[2..5], ["500 Internal Server Error", "-", "-"]);
+    assert!(
+        jvc[5].ends_with(": upstream responded with 500 Internal Server Error"),
+        "{}",
+        jvc[5]
+    );
+    assert_eq!(
+        row(&html, "kadokawa-co-jp"),
+        ["kadokawa-co-jp", "-", "-", "-", "-", "-"]
+    );
+}
+
+#[tokio::test]
+async fn serves_stale_feeds_on_upstream_errors() {
+    let upstream = "https://kemono-friends.sega.jp/news/articles.json";
+    let feeder = Feeder::start_with(None, |config| {
+        config.sources.kemono_friends_sega_jp.stale_if_error = Duration::from_secs(1);
+    });
+    let get = || feeder.client.get(&feeder.url(upstream)).send();
+
+    let res = get().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let fresh = res.text().await.unwrap();
+
+    feeder.mock.failing.store(true, Ordering::SeqCst);
+    let res = get().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers()[WARNING],
+        r#"111 kf-feeder "Revalidation Failed""#
+    );
+    assert_eq!(res.headers()[AGE], "0");
+    assert_eq!(res.text().await.unwrap(), fresh);
+
+    tokio::time::delay_for(Duration::from_millis(1100)).await;
+    let res = get().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(res.headers().get(WARNING).is_none());
+}
+
+#[tokio::test]
+async fn marks_polled_feeds_stale_after_the_interval() {
+    let upstream = "https://kemono-friends.sega.jp/news/articles.json";
+    let feeder = Feeder::start_with(None, |config| {
+        config.feeds = vec![config::Feed {
+            url: upstream.parse().unwrap(),
+            name: None,
+            interval: Some(Duration::from_secs(60)),
+        }];
+    });
+    let insert = |age_secs: u64| {
+        let mut entry = Entry::new(HeaderMap::new(), Bytes::from("feed"), Feed::default());
+        entry.time = SystemTime::now() - Duration::from_secs(age_secs);
+        let lifetime = Duration::from_secs(3600);
+        feeder
+            .state
+            .cache
+            .insert(upstream.to_owned(), entry, lifetime);
+    };
+
+    insert(0);
+    let res = feeder
+        .client
+        .get(&feeder.url(upstream))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.headers().get(WARNING).is_none());
+    assert_eq!(res.text().await.unwrap(), "feed");
+
+    insert(600);
+    let res = feeder
+        .client
+        .get(&feeder.url(upstream))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.headers()[WARNING],
+        r#"111 kf-feeder "Revalidation Failed""#
+    );
+    assert_eq!(res.headers()[AGE], "600");
+    assert_eq!(res.text().await.unwrap(), "feed");
+    assert!(feeder.mock.requests.lock().unwrap().is_empty());
+}
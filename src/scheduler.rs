@@ -0,0 +1,173 @@
+use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use hyper::{
+    header::{HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Method, StatusCode,
+};
+use reqwest::{Request as Reqwest, Url};
+use sha2::{Digest, Sha256};
+use tracing::{error, warn, Instrument};
+
+use crate::{
+    cache::Entry,
+    feed::Feed,
+    router::{self, State},
+    shutdown,
+    transcode::Source,
+    util, webhook, websub,
+};
+
+/// Starts polling the configured feeds in the background.
+///
+/// Feeds persisted in the state directory are loaded into the cache first so that they can be
+/// served before the first poll completes.
+pub fn spawn(state: &Arc<State>) -> std::io::Result<()> {
+    if let Some(ref dir) = state.config.state_dir {
+        fs::create_dir_all(dir.join("feeds"))?;
+    }
+
+    for feed in &state.config.feeds {
+        if let Err(e) = load(state, &feed.url) {
+            warn!(url = %feed.url, error = %e, "failed to load the persisted feed");
+        }
+
+        let interval = state.config.poll_interval(feed);
+        let span = tracing::info_span!("poll", url = %feed.url);
+        tokio::spawn(run(state.clone(), feed.url.clone(), interval).instrument(span));
+    }
+
+    Ok(())
+}
+
+async fn run(state: Arc<State>, url: Url, interval: Duration) {
+    let source = Source::from_url(&url).unwrap();
+    // Validators of the last upstream response, used to make conditional requests.
+    let mut validators = HeaderMap::new();
+    while !shutdown::is_stopping() {
+        let task = shutdown::task();
+        match poll(&state, source, &url, &mut validators).await {
+            Ok(()) => state.polls.succeed(&url),
+            Err(e) => {
+                error!(source = source.name(), error = %e, "failed to poll the feed");
+                state.polls.fail(&url, &e);
+                state.status.failed(source, &e);
+            }
+        }
+        drop(task);
+        tokio::time::delay_for(interval).await;
+    }
+}
+
+async fn poll(
+    state: &Arc<State>,
+    source: Source,
+    url: &Url,
+    validators: &mut HeaderMap,
+) -> anyhow::Result<()> {
+    let mut reqwest = Reqwest::new(Method::GET, url.clone());
+    *reqwest.headers_mut() = validators.clone();
+    let resw = state.upstreams[&source].execute(reqwest).await?;
+
+    match resw.status() {
+        StatusCode::OK => {}
+        StatusCode::NOT_MODIFIED => {
+            if let Some(entry) = state.cache.get(url.as_str()) {
+                let entry = Entry::new(
+                    entry.headers.clone(),
+                    entry.body.clone(),
+                    entry.feed.clone(),
+                );
+                let lifetime = state.config.cache_lifetime(source, url);
+                state.cache.insert(url.to_string(), entry, lifetime);
+                return Ok(());
+            }
+            // The cache has nothing to revalidate. Retry unconditionally next time.
+            validators.clear();
+            anyhow::bail!("unexpected 304 Not Modified");
+        }
+        s => anyhow::bail!("upstream responded with {}", s),
+    }
+
+    validators.clear();
+    if let Some(etag) = resw.headers().get(ETAG) {
+        validators.insert(IF_NONE_MATCH, etag.clone());
+    }
+    if let Some(last_modified) = resw.headers().get(LAST_MODIFIED) {
+        validators.insert(IF_MODIFIED_SINCE, last_modified.clone());
+    }
+
+    let atom = source
+        .transcode_to_vec(url.clone(), resw.bytes_stream())
+        .await?;
+    let atom = Bytes::from(atom);
+    let mut feed = state.produce(source, url, &atom)?;
+    let body = state.history.apply(url, &mut feed, atom).await?;
+    store(state, source, url, body, feed).await
+}
+
+/// Puts the feed `body` into the cache and the state directory, and notifies the subscribers.
+async fn store(
+    state: &Arc<State>,
+    source: Source,
+    url: &Url,
+    body: Bytes,
+    feed: Feed,
+) -> anyhow::Result<()> {
+    let headers = headers(state, source, &body);
+    let entry = Entry::new(headers, body.clone(), feed.clone());
+    let previous = state.cache.get(url.as_str());
+    let lifetime = state.config.cache_lifetime(source, url);
+    state.cache.insert(url.to_string(), entry, lifetime);
+
+    if previous.is_some_and(|previous| previous.body != body) {
+        websub::publish(state, url, body.clone());
+    }
+
+    if let Err(e) = webhook::notify(state, url, &feed).await {
+        warn!(error = %e, "failed to notify the webhooks");
+    }
+
+    if let Some(path) = path(state, url) {
+        util::spawn_blocking(move || util::write_atomic(&path, &body)).await??;
+    }
+
+    Ok(())
+}
+
+fn load(state: &State, url: &Url) -> anyhow::Result<()> {
+    let path = match path(state, url) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let body = match fs::read(&path) {
+        Ok(body) => body,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let time = fs::metadata(&path)?.modified()?;
+
+    let source = Source::from_url(url).unwrap();
+    let feed = Feed::parse(&body)?;
+    let body = Bytes::from(body);
+    let headers = headers(state, source, &body);
+    let mut entry = Entry::new(headers, body, feed);
+    entry.time = time;
+    let lifetime = state.config.cache_lifetime(source, url);
+    state.cache.insert(url.to_string(), entry, lifetime);
+
+    Ok(())
+}
+
+fn headers(state: &State, source: Source, body: &[u8]) -> HeaderMap {
+    let mut headers = router::feed_headers(state.config.sources.get(source).ttl);
+    let etag = format!("\"{}\"", util::hex(&Sha256::digest(body)[..16]));
+    headers.insert(ETAG, HeaderValue::from_str(&etag).unwrap());
+    headers
+}
+
+/// Returns the path where the feed of `url` is persisted, if the state directory is configured.
+fn path(state: &State, url: &Url) -> Option<PathBuf> {
+    let dir = state.config.state_dir.as_ref()?;
+    Some(dir.join("feeds").join(util::slug(url) + ".atom"))
+}
@@ -0,0 +1,102 @@
+//! Graceful shutdown on SIGTERM or Ctrl-C.
+
+#[cfg(test)]
+mod tests;
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    LazyLock,
+};
+
+use tokio::{
+    sync::Notify,
+    time::{self, Instant},
+};
+
+static STOPPING: AtomicBool = AtomicBool::new(false);
+static TASKS: LazyLock<Tasks> = LazyLock::new(Tasks::default);
+
+/// A count of the `Task`s in flight.
+#[derive(Default)]
+struct Tasks {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+/// Keeps the shutdown waiting until it is dropped.
+pub struct Task(&'static Tasks);
+
+pub fn task() -> Task {
+    TASKS.task()
+}
+
+/// Returns whether a shutdown has started, after which no new background work should start.
+pub fn is_stopping() -> bool {
+    STOPPING.load(Ordering::SeqCst)
+}
+
+/// Resolves on SIGTERM or Ctrl-C, marking the feeder as stopping.
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use futures::future::{self, Either};
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                let term = Box::pin(term.recv());
+                let ctrl_c = Box::pin(tokio::signal::ctrl_c());
+                if let Either::Right((Err(e), term)) = future::select(term, ctrl_c).await {
+                    tracing::error!(error = %e, "failed to listen for Ctrl-C");
+                    term.await;
+                }
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "failed to listen for SIGTERM");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+    STOPPING.store(true, Ordering::SeqCst);
+}
+
+/// Waits until every `Task` is dropped or `deadline` passes, and returns the number of the
+/// tasks left.
+pub async fn drain(deadline: Instant) -> usize {
+    TASKS.drain(deadline).await
+}
+
+impl Tasks {
+    fn task(&'static self) -> Task {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        Task(self)
+    }
+
+    async fn drain(&self, deadline: Instant) -> usize {
+        loop {
+            let tasks = self.count.load(Ordering::SeqCst);
+            if tasks == 0 {
+                return 0;
+            }
+            // A notification sent before this wait starts is kept for it.
+            if time::timeout_at(deadline, self.idle.notified())
+                .await
+                .is_err()
+            {
+                return self.count.load(Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify();
+        }
+    }
+}
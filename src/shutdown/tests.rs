@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use tokio::time::{self, Instant};
+
+use super::Tasks;
+
+#[tokio::test]
+async fn drains_tasks() {
+    let tasks: &'static Tasks = Box::leak(Box::default());
+    assert_eq!(tasks.drain(Instant::now()).await, 0);
+
+    let task = tasks.task();
+    let start = Instant::now();
+    tokio::spawn(async move {
+        time::delay_for(Duration::from_millis(50)).await;
+        drop(task);
+    });
+    assert_eq!(tasks.drain(start + Duration::from_secs(5)).await, 0);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    let _task = tasks.task();
+    let deadline = Instant::now() + Duration::from_millis(50);
+    assert_eq!(tasks.drain(deadline).await, 1);
+    assert!(Instant::now() >= deadline);
+}
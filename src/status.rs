@@ -0,0 +1,124 @@
+//! An HTML page summarizing the recent activity of each source.
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write as _},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use hyper::{
+    header::{CACHE_CONTROL, CONTENT_TYPE},
+    Body, Response, StatusCode,
+};
+
+use crate::{date, router::State, transcode::Source, util};
+
+pub const PATH: &str = "/status";
+
+/// The latest events of each source.
+#[derive(Default)]
+pub struct Status(Mutex<HashMap<Source, Events>>);
+
+#[derive(Clone, Default)]
+struct Events {
+    /// When the upstream was last requested.
+    fetched: Option<SystemTime>,
+    /// Status code of the last upstream response, `None` if the last request failed.
+    upstream: Option<StatusCode>,
+    /// Number of entries in the last feed produced.
+    entries: Option<usize>,
+    error: Option<(SystemTime, String)>,
+}
+
+impl Status {
+    /// Records a request to the upstream of `source` and its outcome.
+    pub fn fetched(&self, source: Source, result: Result<StatusCode, &dyn Display>) {
+        let mut sources = self.0.lock().unwrap();
+        let events = sources.entry(source).or_default();
+        let now = SystemTime::now();
+        events.fetched = Some(now);
+        match result {
+            Ok(status) => events.upstream = Some(status),
+            Err(e) => {
+                events.upstream = None;
+                events.error = Some((now, e.to_string()));
+            }
+        }
+    }
+
+    /// Records the number of entries of a feed produced for `source`.
+    pub fn produced(&self, source: Source, entries: usize) {
+        self.0.lock().unwrap().entry(source).or_default().entries = Some(entries);
+    }
+
+    /// Records an error in fetching or transcoding a feed of `source`.
+    pub fn failed(&self, source: Source, error: &dyn Display) {
+        let mut sources = self.0.lock().unwrap();
+        let events = sources.entry(source).or_default();
+        events.error = Some((SystemTime::now(), error.to_string()));
+    }
+}
+
+pub fn handle(state: &State) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "text/html; charset=utf-8")
+        .header(CACHE_CONTROL, "no-store")
+        .body(Body::from(render(state)))
+        .unwrap()
+}
+
+fn render(state: &State) -> String {
+    let sources = state.status.0.lock().unwrap().clone();
+
+    // The age of the freshest feed in the cache for each source.
+    let mut cache_ages = HashMap::new();
+    for (url, age) in state.cache.ages() {
+        if let Some(source) = url.parse().ok().as_ref().and_then(Source::from_url) {
+            let min = cache_ages.entry(source).or_insert(age);
+            *min = age.min(*min);
+        }
+    }
+
+    let mut html = String::from(concat!(
+        "<!DOCTYPE html>\n",
+        "<html lang=\"en\">\n",
+        "<meta charset=\"utf-8\">\n",
+        "<title>kf-feeder status</title>\n",
+        "<h1>Sources</h1>\n",
+        "<table>\n",
+        "<tr><th>Source<th>Last fetch<th>Upstream status<th>Entries<th>Cache age<th>Last error\n",
+    ));
+    for &source in &Source::ALL {
+        let events = sources.get(&source).cloned().unwrap_or_default();
+        let upstream = match (events.fetched, events.upstream) {
+            (None, _) => "-".to_owned(),
+            (Some(_), None) => "failed".to_owned(),
+            (Some(_), Some(status)) => status.to_string(),
+        };
+        let error = match events.error {
+            Some((time, ref message)) => format!("{}: {}", date::format_jst(time), message),
+            None => "-".to_owned(),
+        };
+        writeln!(
+            html,
+            "<tr><td>{}<td>{}<td>{}<td>{}<td>{}<td>{}",
+            source.name(),
+            events
+                .fetched
+                .map_or_else(|| "-".to_owned(), date::format_jst),
+            util::escape_html(&upstream),
+            events
+                .entries
+                .map_or_else(|| "-".to_owned(), |n| n.to_string()),
+            cache_ages
+                .get(&source)
+                .map_or_else(|| "-".to_owned(), |age| format!("{}s", age.as_secs())),
+            util::escape_html(&error),
+        )
+        .unwrap();
+    }
+    html.push_str("</table>\n");
+    html
+}
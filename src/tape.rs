@@ -0,0 +1,109 @@
+//! Recording and replaying of upstream responses.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    StatusCode,
+};
+use reqwest::{Response as Reswponse, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::util;
+
+#[derive(Debug)]
+pub enum Tape {
+    /// Saves every upstream response and produced feed into the directory.
+    Record(PathBuf),
+    /// Serves upstream responses from the directory instead of fetching them.
+    Replay(PathBuf),
+}
+
+#[derive(Deserialize, Serialize)]
+struct Head {
+    url: Url,
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+impl Tape {
+    /// Saves the feed produced from the upstream `url`.
+    pub fn record_feed(&self, url: &Url, atom: Bytes) {
+        if let Tape::Record(ref dir) = *self {
+            let path = path(dir, url, "atom");
+            let url = url.clone();
+            util::spawn_blocking(move || {
+                if let Err(e) = util::write_atomic(&path, &atom) {
+                    tracing::warn!(%url, error = %e, "failed to record the feed");
+                }
+            });
+        }
+    }
+}
+
+/// Saves `resw` for the upstream `url` and returns an equivalent response.
+pub async fn record(dir: &Path, url: &Url, resw: Reswponse) -> reqwest::Result<Reswponse> {
+    if resw.status() == StatusCode::NOT_MODIFIED {
+        return Ok(resw);
+    }
+
+    let status = resw.status();
+    let headers = resw.headers().clone();
+    let body = resw.bytes().await?;
+
+    let head = Head {
+        url: url.clone(),
+        status: status.as_u16(),
+        headers: headers
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.as_str().to_owned(), value)
+            })
+            .collect(),
+    };
+    let (head_path, body_path) = (path(dir, url, "head.json"), path(dir, url, "body"));
+    let contents = body.clone();
+    let result = util::spawn_blocking(move || -> anyhow::Result<()> {
+        util::write_atomic(&head_path, &json::to_vec(&head)?)?;
+        util::write_atomic(&body_path, &contents)?;
+        Ok(())
+    })
+    .await
+    .unwrap();
+    if let Err(e) = result {
+        tracing::warn!(%url, error = %e, "failed to record the response");
+    }
+
+    let mut res = hyper::Response::new(body);
+    *res.status_mut() = status;
+    *res.headers_mut() = headers;
+    Ok(res.into())
+}
+
+/// Returns the recorded response for the upstream `url`.
+pub async fn replay(dir: &Path, url: &Url) -> anyhow::Result<Reswponse> {
+    let (head_path, body_path) = (path(dir, url, "head.json"), path(dir, url, "body"));
+    let (head, body) = util::spawn_blocking(move || -> anyhow::Result<_> {
+        let head = json::from_slice::<Head>(&fs::read(head_path)?)?;
+        let body = fs::read(body_path)?;
+        Ok((head, body))
+    })
+    .await
+    .unwrap()?;
+
+    let mut res = hyper::Response::builder().status(StatusCode::from_u16(head.status)?);
+    for (name, value) in &head.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())?;
+        res = res.header(name, HeaderValue::from_str(value)?);
+    }
+    Ok(res.body(Bytes::from(body))?.into())
+}
+
+fn path(dir: &Path, url: &Url, extension: &str) -> PathBuf {
+    dir.join(format!("{}.{}", util::slug(url), extension))
+}
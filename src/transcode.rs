@@ -2,13 +2,157 @@ pub mod jvcmusic_co_jp;
 pub mod kadokawa_co_jp;
 pub mod kemono_friends_sega_jp;
 
-use std::future::Future;
+#[cfg(test)]
+mod tests;
 
+use std::{future::Future, str::FromStr, time::Instant};
+
+use auto_enums::auto_enum;
 use bytes::Bytes;
-use futures::Stream;
-use hyper::body::Sender;
+use futures::{future, Stream};
+use hyper::{body::Sender, Body};
 use reqwest::Url;
 
+use crate::metrics;
+
+/// An upstream Web site that the feeder knows how to transcode.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Source {
+    KemonoFriendsSegaJp,
+    KadokawaCoJp,
+    JvcmusicCoJp,
+}
+
+impl Source {
+    pub const ALL: [Source; 3] = [
+        Source::KemonoFriendsSegaJp,
+        Source::KadokawaCoJp,
+        Source::JvcmusicCoJp,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::KemonoFriendsSegaJp => "kemono-friends-sega-jp",
+            Source::KadokawaCoJp => "kadokawa-co-jp",
+            Source::JvcmusicCoJp => "jvcmusic-co-jp",
+        }
+    }
+
+    /// Returns the upstream URL of the main feed of this source, if there is a single one.
+    pub fn default_url(self) -> Option<&'static str> {
+        match self {
+            Source::KemonoFriendsSegaJp => {
+                Some("https://kemono-friends.sega.jp/news/articles.json")
+            }
+            Source::KadokawaCoJp => Some("https://www.kadokawa.co.jp/json.jsp?id=342"),
+            // Every artist has a feed of their own.
+            Source::JvcmusicCoJp => None,
+        }
+    }
+
+    /// Returns the source whose transcoder handles the upstream `url`.
+    pub fn from_url(url: &Url) -> Option<Self> {
+        let path = &url[..url::Position::AfterPath];
+
+        match path {
+            "https://kemono-friends.sega.jp/news/articles.json" => {
+                return Some(Source::KemonoFriendsSegaJp);
+            }
+            "https://www.kadokawa.co.jp/json.jsp" => {
+                if let Some(q) = url.query() {
+                    for pair in q.split('&') {
+                        if pair.starts_with("id=") && pair[3..] == *"342" {
+                            return Some(Source::KadokawaCoJp);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let code = path
+            .strip_prefix("https://www.jvcmusic.co.jp/-/News/A")
+            .and_then(|rest| rest.strip_suffix(".json"));
+        if let Some(code) = code {
+            if code.len() == 6 && code.bytes().all(|c| c.is_ascii_digit()) {
+                return Some(Source::JvcmusicCoJp);
+            }
+        }
+
+        None
+    }
+
+    /// Transcodes `input` with the transcoder of this source.
+    pub fn transcode<I>(
+        self,
+        url: Url,
+        input: I,
+        output: Sender,
+    ) -> impl Future<Output = json::Result<()>>
+    where
+        I: Stream<Item = reqwest::Result<Bytes>> + Send + Unpin + 'static,
+    {
+        let start = Instant::now();
+        let task = self.transcoder(url, input, output);
+        async move {
+            let result = task.await;
+            metrics::transcode(self, start.elapsed());
+            result
+        }
+    }
+
+    #[auto_enum(Future)]
+    fn transcoder<I>(
+        self,
+        url: Url,
+        input: I,
+        output: Sender,
+    ) -> impl Future<Output = json::Result<()>>
+    where
+        I: Stream<Item = reqwest::Result<Bytes>> + Send + Unpin + 'static,
+    {
+        match self {
+            Source::KemonoFriendsSegaJp => {
+                kemono_friends_sega_jp::Transcode.transcode(url, input, output)
+            }
+            Source::KadokawaCoJp => kadokawa_co_jp::Transcode.transcode(url, input, output),
+            Source::JvcmusicCoJp => jvcmusic_co_jp::Transcode.transcode(url, input, output),
+        }
+    }
+
+    /// Transcodes `input` into an in-memory Atom document.
+    pub async fn transcode_to_vec<I>(self, url: Url, input: I) -> anyhow::Result<Vec<u8>>
+    where
+        I: Stream<Item = reqwest::Result<Bytes>> + Send + Unpin + 'static,
+    {
+        let (tx, body) = Body::channel();
+        let task = self.transcode(url, input, tx);
+        let (result, body) = future::join(task, hyper::body::to_bytes(body)).await;
+        result?;
+        Ok(body?.to_vec())
+    }
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        Source::ALL
+            .iter()
+            .copied()
+            .find(|source| source.name() == s)
+            .ok_or_else(|| {
+                let names = Source::ALL.iter().map(|source| source.name());
+                format!(
+                    "unknown source `{}` (expected one of {})",
+                    s,
+                    names.collect::<Vec<_>>().join(", ")
+                )
+            })
+    }
+}
+
 pub trait Transcode {
     type Future: Future<Output = Result<(), Self::Error>>;
     type Error;
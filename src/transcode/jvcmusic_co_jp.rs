@@ -28,7 +28,7 @@ impl super::Transcode for Transcode {
     {
         let mut d = json::Deserializer::from_reader(StreamRead::new(input));
         let t = Transcoder::new(BodyWrite::new(output));
-        JoinHandle(tokio::task::spawn_blocking(move || t.deserialize(&mut d)))
+        JoinHandle(spawn_blocking(move || t.deserialize(&mut d)))
     }
 }
 
@@ -64,56 +64,69 @@ impl<'de, W: Write> DeserializeSeed<'de> for Transcoder<W> {
                     Other,
                 }
 
-                feed(&mut self.0, |writer| {
-                    while let Some(key) = a.next_key::<Key>()? {
-                        match key {
-                            Key::Title => {
-                                let title = a.next_value::<String>()?;
-                                tag(writer, BytesStart::borrowed_name(b"title"), |writer| {
-                                    writer
-                                        .write_event(&Event::Text(BytesText::from_plain_str(
-                                            &title,
-                                        )))
-                                        .map_err(de::Error::custom)?;
-                                    Ok(())
-                                })?;
-                            }
-                            Key::Description => {
-                                let description = a.next_value::<String>()?;
-                                tag(writer, BytesStart::borrowed_name(b"subtitle"), |writer| {
-                                    writer
-                                        .write_event(&Event::Text(BytesText::from_plain_str(
-                                            &description,
-                                        )))
-                                        .map_err(de::Error::custom)?;
-                                    Ok(())
-                                })?;
-                            }
-                            Key::Url => {
-                                let href = a.next_value::<String>()?;
-                                let link = format!(r#"link href="{}""#, href);
-                                writer
-                                    .write_event(Event::Empty(BytesStart::owned(link, 4)))
-                                    .map_err(de::Error::custom)?;
-                                tag(writer, BytesStart::borrowed_name(b"id"), |writer| {
-                                    let id = format!(
-                                        "tag:ursus.cauda.elongata@gmail.com,2019:proxy:{}",
-                                        href
-                                    );
+                feed(
+                    &mut self.0,
+                    "JVCKENWOOD Victor Entertainment",
+                    |writer, updated| {
+                        while let Some(key) = a.next_key::<Key>()? {
+                            match key {
+                                Key::Title => {
+                                    let title = a.next_value::<String>()?;
+                                    tag(writer, BytesStart::borrowed_name(b"title"), |writer| {
+                                        writer
+                                            .write_event(Event::Text(BytesText::from_plain_str(
+                                                &title,
+                                            )))
+                                            .map_err(de::Error::custom)?;
+                                        Ok(())
+                                    })?;
+                                }
+                                Key::Description => {
+                                    let description = a.next_value::<String>()?;
+                                    tag(
+                                        writer,
+                                        BytesStart::borrowed_name(b"subtitle"),
+                                        |writer| {
+                                            writer
+                                                .write_event(Event::Text(
+                                                    BytesText::from_plain_str(&description),
+                                                ))
+                                                .map_err(de::Error::custom)?;
+                                            Ok(())
+                                        },
+                                    )?;
+                                }
+                                Key::Url => {
+                                    let href = a.next_value::<String>()?;
+                                    let mut link = BytesStart::borrowed_name(b"link");
+                                    link.push_attribute(("href", &*href));
                                     writer
-                                        .write_event(Event::Text(BytesText::from_escaped_str(id)))
+                                        .write_event(Event::Empty(link))
                                         .map_err(de::Error::custom)?;
-                                    Ok(())
-                                })?;
-                            }
-                            Key::Contents => a.next_value_seed(DeserializeContents(writer))?,
-                            Key::Other => {
-                                a.next_value::<de::IgnoredAny>()?;
+                                    tag(writer, BytesStart::borrowed_name(b"id"), |writer| {
+                                        let id = format!(
+                                            "tag:ursus.cauda.elongata@gmail.com,2019:proxy:{}",
+                                            href
+                                        );
+                                        writer
+                                            .write_event(Event::Text(BytesText::from_plain_str(
+                                                &id,
+                                            )))
+                                            .map_err(de::Error::custom)?;
+                                        Ok(())
+                                    })?;
+                                }
+                                Key::Contents => {
+                                    a.next_value_seed(DeserializeContents(writer, updated))?
+                                }
+                                Key::Other => {
+                                    a.next_value::<de::IgnoredAny>()?;
+                                }
                             }
                         }
-                    }
-                    Ok(())
-                })
+                        Ok(())
+                    },
+                )
             }
         }
 
@@ -121,13 +134,13 @@ impl<'de, W: Write> DeserializeSeed<'de> for Transcoder<W> {
     }
 }
 
-struct DeserializeContents<'a, W: Write>(&'a mut xml::Writer<W>);
+struct DeserializeContents<'a, W: Write>(&'a mut xml::Writer<W>, &'a mut Option<String>);
 
 impl<'a, 'de, W: Write> DeserializeSeed<'de> for DeserializeContents<'a, W> {
     type Value = ();
 
     fn deserialize<D: de::Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
-        struct Visitor<'a, W: Write>(&'a mut xml::Writer<W>);
+        struct Visitor<'a, W: Write>(&'a mut xml::Writer<W>, &'a mut Option<String>);
         impl<'a, 'de, W: Write> de::Visitor<'de> for Visitor<'a, W> {
             type Value = ();
 
@@ -147,7 +160,7 @@ impl<'a, 'de, W: Write> DeserializeSeed<'de> for DeserializeContents<'a, W> {
                 while let Some(key) = a.next_key::<Key>()? {
                     if let Key::Articles = key {
                         return a
-                            .next_value_seed(DeserializeArticles(self.0))
+                            .next_value_seed(DeserializeArticles(self.0, self.1))
                             .and_then(|()| {
                                 while let Some((de::IgnoredAny, de::IgnoredAny)) = a.next_entry()? {
                                 }
@@ -162,43 +175,46 @@ impl<'a, 'de, W: Write> DeserializeSeed<'de> for DeserializeContents<'a, W> {
             }
         }
 
-        d.deserialize_map(Visitor(self.0))
+        d.deserialize_map(Visitor(self.0, self.1))
     }
 }
 
-struct DeserializeArticles<'a, W: Write>(&'a mut xml::Writer<W>);
+struct DeserializeArticles<'a, W: Write>(&'a mut xml::Writer<W>, &'a mut Option<String>);
 
 impl<'a, 'de, W: Write> DeserializeSeed<'de> for DeserializeArticles<'a, W> {
     type Value = ();
 
     fn deserialize<D: de::Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
-        struct Visitor<'a, W: Write>(&'a mut xml::Writer<W>);
+        struct Visitor<'a, W: Write>(&'a mut xml::Writer<W>, &'a mut Option<String>);
         impl<'a, 'de, W: Write> de::Visitor<'de> for Visitor<'a, W> {
             type Value = ();
             fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
                 write!(f, "an array")
             }
             fn visit_seq<A: de::SeqAccess<'de>>(self, mut a: A) -> Result<(), A::Error> {
-                while let Some(()) = a.next_element_seed(DeserializeArticle(self.0))? {}
+                while let Some(()) =
+                    a.next_element_seed(DeserializeArticle(&mut *self.0, &mut *self.1))?
+                {
+                }
                 Ok(())
             }
         }
 
-        d.deserialize_seq(Visitor(self.0))
+        d.deserialize_seq(Visitor(self.0, self.1))
     }
 }
 
-struct DeserializeArticle<'a, W: Write>(&'a mut xml::Writer<W>);
+struct DeserializeArticle<'a, W: Write>(&'a mut xml::Writer<W>, &'a mut Option<String>);
 
 impl<'de, 'a, W: Write> DeserializeSeed<'de> for DeserializeArticle<'a, W> {
     type Value = ();
 
     fn deserialize<D: de::Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
-        d.deserialize_map(ArticleVisitor(self.0))
+        d.deserialize_map(ArticleVisitor(self.0, self.1))
     }
 }
 
-struct ArticleVisitor<'a, W: Write>(&'a mut xml::Writer<W>);
+struct ArticleVisitor<'a, W: Write>(&'a mut xml::Writer<W>, &'a mut Option<String>);
 
 impl<'de, 'a, W: Write> de::Visitor<'de> for ArticleVisitor<'a, W> {
     type Value = ();
@@ -219,6 +235,7 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for ArticleVisitor<'a, W> {
             Other,
         }
 
+        let latest = self.1;
         tag(self.0, BytesStart::borrowed_name(b"entry"), |writer| {
             while let Some(key) = a.next_key::<Key>()? {
                 match key {
@@ -228,19 +245,20 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for ArticleVisitor<'a, W> {
                             let id =
                                 format!("tag:ursus.cauda.elongata@gmail.com,2019:proxy:{}", url);
                             writer
-                                .write_event(&Event::Text(BytesText::from_plain_str(&id)))
+                                .write_event(Event::Text(BytesText::from_plain_str(&id)))
                                 .map_err(de::Error::custom)?;
                             Ok(())
                         })?;
-                        let link = format!(r#"link href="{}""#, url);
+                        let mut link = BytesStart::borrowed_name(b"link");
+                        link.push_attribute(("href", &*url));
                         writer
-                            .write_event(Event::Empty(BytesStart::owned(link, 4)))
+                            .write_event(Event::Empty(link))
                             .map_err(de::Error::custom)?;
                     }
                     Key::Title => tag(writer, BytesStart::borrowed_name(b"title"), |writer| {
                         let title = a.next_value::<String>()?;
                         writer
-                            .write_event(&Event::Text(BytesText::from_plain_str(&title)))
+                            .write_event(Event::Text(BytesText::from_plain_str(&title)))
                             .map_err(de::Error::custom)?;
                         Ok(())
                     })?,
@@ -249,30 +267,17 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for ArticleVisitor<'a, W> {
                         let start = BytesStart::borrowed(br#"content type="text""#, 7);
                         tag(writer, start, |writer| {
                             writer
-                                .write_event(&Event::CData(BytesText::from_plain_str(&text)))
+                                .write_event(Event::CData(BytesText::from_plain_str(&text)))
                                 .map_err(de::Error::custom)?;
                             Ok(())
                         })?;
                     }
                     Key::OpenDt => {
-                        let mut date = a.next_value::<String>()?;
-                        if date.get(10..11) != Some(" ") {
-                            return Err(de::Error::custom("unrecognized `open_dt`"));
+                        if let Some(date) = normalize_date(&a.next_value::<String>()?) {
+                            text(writer, b"published", &date)?;
+                            text(writer, b"updated", &date)?;
+                            raise(latest, &date);
                         }
-                        date.replace_range(10..11, "T");
-                        date.push_str("+09:00");
-                        tag(writer, BytesStart::borrowed_name(b"published"), |writer| {
-                            writer
-                                .write_event(&Event::Text(BytesText::from_plain_str(&date)))
-                                .map_err(de::Error::custom)?;
-                            Ok(())
-                        })?;
-                        tag(writer, BytesStart::borrowed_name(b"updated"), |writer| {
-                            writer
-                                .write_event(&Event::Text(BytesText::from_plain_str(&date)))
-                                .map_err(de::Error::custom)?;
-                            Ok(())
-                        })?;
                     }
                     Key::Other => {
                         a.next_value::<de::IgnoredAny>()?;
@@ -28,7 +28,7 @@ impl super::Transcode for Transcode {
     {
         let mut d = json::Deserializer::from_reader(StreamRead::new(input));
         let t = Transcoder::new(BodyWrite::new(output), url);
-        JoinHandle(tokio::task::spawn_blocking(move || t.deserialize(&mut d)))
+        JoinHandle(spawn_blocking(move || t.deserialize(&mut d)))
     }
 }
 
@@ -54,10 +54,10 @@ impl<'de, W: Write> DeserializeSeed<'de> for Transcoder<W> {
 
             fn visit_seq<A: de::SeqAccess<'de>>(mut self, mut a: A) -> Result<(), A::Error> {
                 let uri = self.1;
-                feed(&mut self.0, |writer| {
+                feed(&mut self.0, "KADOKAWA", |writer, updated| {
                     tag(writer, BytesStart::borrowed_name(b"title"), |writer| {
                         writer
-                            .write_event(&Event::Text(BytesText::from_escaped_str(
+                            .write_event(Event::Text(BytesText::from_escaped_str(
                                 "検索結果一覧 | KADOKAWA",
                             )))
                             .map_err(de::Error::custom)?;
@@ -85,7 +85,6 @@ impl<'de, W: Write> DeserializeSeed<'de> for Transcoder<W> {
                     writer
                         .write_event(Event::Empty(BytesStart::owned(link, 4)))
                         .map_err(de::Error::custom)?;
-                    // tag(writer, BytesStart::borrowed_name(b"updated"), |writer| Ok(()))?;
                     tag(writer, BytesStart::borrowed_name(b"id"), |writer| {
                         let id = format!("tag:ursus.cauda.elongata@gmail.com,2019:proxy:{}", href);
                         writer
@@ -93,7 +92,7 @@ impl<'de, W: Write> DeserializeSeed<'de> for Transcoder<W> {
                             .map_err(de::Error::custom)?;
                         Ok(())
                     })?;
-                    while let Some(()) = a.next_element_seed(DeserializeEntry(writer))? {}
+                    while let Some(()) = a.next_element_seed(DeserializeEntry(writer, updated))? {}
                     Ok(())
                 })
             }
@@ -103,17 +102,17 @@ impl<'de, W: Write> DeserializeSeed<'de> for Transcoder<W> {
     }
 }
 
-struct DeserializeEntry<'a, W: Write>(&'a mut xml::Writer<W>);
+struct DeserializeEntry<'a, W: Write>(&'a mut xml::Writer<W>, &'a mut Option<String>);
 
 impl<'de, 'a, W: Write> DeserializeSeed<'de> for DeserializeEntry<'a, W> {
     type Value = ();
 
     fn deserialize<D: de::Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
-        d.deserialize_map(EntryVisitor(self.0))
+        d.deserialize_map(EntryVisitor(self.0, self.1))
     }
 }
 
-struct EntryVisitor<'a, W: Write>(&'a mut xml::Writer<W>);
+struct EntryVisitor<'a, W: Write>(&'a mut xml::Writer<W>, &'a mut Option<String>);
 
 impl<'de, 'a, W: Write> de::Visitor<'de> for EntryVisitor<'a, W> {
     type Value = ();
@@ -122,7 +121,7 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for EntryVisitor<'a, W> {
         write!(f, "an object")
     }
 
-    fn visit_map<A: de::MapAccess<'de>>(mut self, mut a: A) -> Result<(), A::Error> {
+    fn visit_map<A: de::MapAccess<'de>>(self, mut a: A) -> Result<(), A::Error> {
         #[derive(Deserialize)]
         #[serde(rename_all = "camelCase")]
         enum Key {
@@ -135,7 +134,8 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for EntryVisitor<'a, W> {
             Other,
         }
 
-        tag(&mut self.0, BytesStart::borrowed_name(b"entry"), |writer| {
+        let latest = self.1;
+        tag(self.0, BytesStart::borrowed_name(b"entry"), |writer| {
             while let Some(key) = a.next_key::<Key>()? {
                 match key {
                     Key::ItemCode => {
@@ -143,7 +143,7 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for EntryVisitor<'a, W> {
                         tag(writer, BytesStart::borrowed_name(b"id"), |writer| {
                             let id = format!("tag:ursus.cauda.elongata@gmail.com,2019:proxy:https://www.kadokawa.co.jp/product/{}/", code);
                             writer
-                                .write_event(&Event::Text(BytesText::from_plain_str(&id)))
+                                .write_event(Event::Text(BytesText::from_plain_str(&id)))
                                 .map_err(de::Error::custom)?;
                             Ok(())
                         })?;
@@ -151,13 +151,13 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for EntryVisitor<'a, W> {
                         let href = format!("https://www.kadokawa.co.jp/product/{}/", code);
                         link.push_attribute(("href", &*href));
                         writer
-                            .write_event(&Event::Empty(link))
+                            .write_event(Event::Empty(link))
                             .map_err(de::Error::custom)?;
                     }
                     Key::Title => tag(writer, BytesStart::borrowed_name(b"title"), |writer| {
                         let title = a.next_value::<String>()?;
                         writer
-                            .write_event(&Event::Text(BytesText::from_plain_str(&title)))
+                            .write_event(Event::Text(BytesText::from_plain_str(&title)))
                             .map_err(de::Error::custom)?;
                         Ok(())
                     })?,
@@ -166,7 +166,7 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for EntryVisitor<'a, W> {
                         tag(writer, start, |writer| {
                             let text = a.next_value::<String>()?;
                             writer
-                                .write_event(&Event::CData(BytesText::from_plain_str(&text)))
+                                .write_event(Event::CData(BytesText::from_plain_str(&text)))
                                 .map_err(de::Error::custom)?;
                             Ok(())
                         })?;
@@ -175,26 +175,17 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for EntryVisitor<'a, W> {
                         tag(writer, BytesStart::borrowed_name(b"name"), |writer| {
                             let name = a.next_value::<String>()?;
                             writer
-                                .write_event(&Event::Text(BytesText::from_plain_str(&name)))
+                                .write_event(Event::Text(BytesText::from_plain_str(&name)))
                                 .map_err(de::Error::custom)?;
                             Ok(())
                         })
                     })?,
                     Key::PublicationDate => {
-                        let mut date = a.next_value::<String>()?;
-                        date.push_str("T00:00:00+09:00");
-                        tag(writer, BytesStart::borrowed_name(b"published"), |writer| {
-                            writer
-                                .write_event(&Event::Text(BytesText::from_escaped_str(&date)))
-                                .map_err(de::Error::custom)?;
-                            Ok(())
-                        })?;
-                        tag(writer, BytesStart::borrowed_name(b"updated"), |writer| {
-                            writer
-                                .write_event(&Event::Text(BytesText::from_escaped_str(&date)))
-                                .map_err(de::Error::custom)?;
-                            Ok(())
-                        })?;
+                        if let Some(date) = normalize_date(&a.next_value::<String>()?) {
+                            text(writer, b"published", &date)?;
+                            text(writer, b"updated", &date)?;
+                            raise(latest, &date);
+                        }
                     }
                     Key::Other => {
                         a.next_value::<de::IgnoredAny>()?;
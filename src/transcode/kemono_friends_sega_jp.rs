@@ -28,7 +28,7 @@ impl super::Transcode for Transcode {
     {
         let mut d = json::Deserializer::from_reader(StreamRead::new(input));
         let t = Transcoder::new(BodyWrite::new(output));
-        JoinHandle(tokio::task::spawn_blocking(move || t.deserialize(&mut d)))
+        JoinHandle(spawn_blocking(move || t.deserialize(&mut d)))
     }
 }
 
@@ -96,10 +96,10 @@ impl<'de, W: Write> DeserializeSeed<'de> for DeserializeArticles<W> {
                 write!(f, "an array")
             }
             fn visit_seq<A: de::SeqAccess<'de>>(mut self, mut a: A) -> Result<(), A::Error> {
-                feed(&mut self.0, |writer| {
+                feed(&mut self.0, "SEGA", |writer, updated| {
                     tag(writer, BytesStart::borrowed_name(b"title"), |writer| {
                         writer
-                            .write_event(&Event::Text(BytesText::from_escaped_str(
+                            .write_event(Event::Text(BytesText::from_escaped_str(
                                 "けものフレンズ３",
                             )))
                             .map_err(de::Error::custom)?;
@@ -111,7 +111,6 @@ impl<'de, W: Write> DeserializeSeed<'de> for DeserializeArticles<W> {
                             4,
                         )))
                         .map_err(de::Error::custom)?;
-                    // tag(writer, BytesStart::borrowed_name(b"updated"), |writer| Ok(()))?;
                     tag(writer, BytesStart::borrowed_name(b"id"), |writer| {
                         const ID: &str = "tag:ursus.cauda.elongata@gmail.com,2019:proxy:https://kemono-friends.sega.jp/";
                         writer
@@ -119,7 +118,8 @@ impl<'de, W: Write> DeserializeSeed<'de> for DeserializeArticles<W> {
                             .map_err(de::Error::custom)?;
                         Ok(())
                     })?;
-                    while let Some(()) = a.next_element_seed(DeserializeArticle(writer))? {}
+                    while let Some(()) = a.next_element_seed(DeserializeArticle(writer, updated))? {
+                    }
                     Ok(())
                 })
             }
@@ -129,17 +129,17 @@ impl<'de, W: Write> DeserializeSeed<'de> for DeserializeArticles<W> {
     }
 }
 
-struct DeserializeArticle<'a, W: Write>(&'a mut xml::Writer<W>);
+struct DeserializeArticle<'a, W: Write>(&'a mut xml::Writer<W>, &'a mut Option<String>);
 
 impl<'de, 'a, W: Write> DeserializeSeed<'de> for DeserializeArticle<'a, W> {
     type Value = ();
 
     fn deserialize<D: de::Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
-        d.deserialize_map(ArticleVisitor(self.0))
+        d.deserialize_map(ArticleVisitor(self.0, self.1))
     }
 }
 
-struct ArticleVisitor<'a, W: Write>(&'a mut xml::Writer<W>);
+struct ArticleVisitor<'a, W: Write>(&'a mut xml::Writer<W>, &'a mut Option<String>);
 
 impl<'de, 'a, W: Write> de::Visitor<'de> for ArticleVisitor<'a, W> {
     type Value = ();
@@ -148,7 +148,7 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for ArticleVisitor<'a, W> {
         write!(f, "a map")
     }
 
-    fn visit_map<A: de::MapAccess<'de>>(mut self, mut a: A) -> Result<(), A::Error> {
+    fn visit_map<A: de::MapAccess<'de>>(self, mut a: A) -> Result<(), A::Error> {
         #[derive(Deserialize)]
         #[serde(rename_all = "snake_case")]
         enum Key {
@@ -161,7 +161,10 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for ArticleVisitor<'a, W> {
             Other,
         }
 
-        tag(&mut self.0, BytesStart::borrowed_name(b"entry"), |writer| {
+        let latest = self.1;
+        tag(self.0, BytesStart::borrowed_name(b"entry"), |writer| {
+            let mut published = None;
+            let mut updated = None;
             while let Some(key) = a.next_key::<Key>()? {
                 match key {
                     Key::Id => {
@@ -169,7 +172,7 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for ArticleVisitor<'a, W> {
                         tag(writer, BytesStart::borrowed_name(b"id"), |writer| {
                             let id = format!("tag:ursus.cauda.elongata@gmail.com,2019:proxy:https://kemono-friends.sega.jp/news/{}/", id);
                             writer
-                                .write_event(&Event::Text(BytesText::from_plain_str(&id)))
+                                .write_event(Event::Text(BytesText::from_plain_str(&id)))
                                 .map_err(de::Error::custom)?;
                             Ok(())
                         })?;
@@ -177,7 +180,7 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for ArticleVisitor<'a, W> {
                         let href = format!("https://kemono-friends.sega.jp/news/{}/", id);
                         link.push_attribute(("href", &*href));
                         writer
-                            .write_event(&Event::Empty(link))
+                            .write_event(Event::Empty(link))
                             .map_err(de::Error::custom)?;
                     }
                     Key::Categories => {
@@ -185,40 +188,44 @@ impl<'de, 'a, W: Write> de::Visitor<'de> for ArticleVisitor<'a, W> {
                             let mut category = BytesStart::borrowed_name(b"category");
                             category.push_attribute(("term", &**c));
                             writer
-                                .write_event(&Event::Empty(category))
+                                .write_event(Event::Empty(category))
                                 .map_err(de::Error::custom)?;
                         }
                     }
                     Key::Title => tag(writer, BytesStart::borrowed_name(b"title"), |writer| {
                         let title = a.next_value::<String>()?;
                         writer
-                            .write_event(&Event::Text(BytesText::from_plain_str(&title)))
-                            .map_err(de::Error::custom)?;
-                        Ok(())
-                    })?,
-                    Key::Date => tag(writer, BytesStart::borrowed_name(b"published"), |writer| {
-                        let mut date = a.next_value::<String>()?;
-                        date.push_str("+09:00");
-                        writer
-                            .write_event(&Event::Text(BytesText::from_plain_str(&date)))
+                            .write_event(Event::Text(BytesText::from_plain_str(&title)))
                             .map_err(de::Error::custom)?;
                         Ok(())
                     })?,
+                    Key::Date => {
+                        if let Some(date) = normalize_date(&a.next_value::<String>()?) {
+                            text(writer, b"published", &date)?;
+                            published = Some(date);
+                        }
+                    }
                     Key::Modified => {
-                        tag(writer, BytesStart::borrowed_name(b"updated"), |writer| {
-                            let mut modified = a.next_value::<String>()?;
-                            modified.push_str("+09:00");
-                            writer
-                                .write_event(&Event::Text(BytesText::from_plain_str(&modified)))
-                                .map_err(de::Error::custom)?;
-                            Ok(())
-                        })?
+                        if let Some(modified) = normalize_date(&a.next_value::<String>()?) {
+                            text(writer, b"updated", &modified)?;
+                            updated = Some(modified);
+                        }
                     }
                     Key::Other => {
                         a.next_value::<de::IgnoredAny>()?;
                     }
                 }
             }
+            // Entries that have never been modified lack `modified`.
+            if updated.is_none() {
+                if let Some(ref published) = published {
+                    text(writer, b"updated", published)?;
+                }
+                updated = published;
+            }
+            if let Some(ref updated) = updated {
+                raise(latest, updated);
+            }
             Ok(())
         })
     }
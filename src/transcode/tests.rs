@@ -0,0 +1,120 @@
+//! Golden tests of the transcoders.
+//!
+//! Each `tests/fixtures/<source>/<name>.json` is an upstream document, and `<name>.atom` next to
+//! it is the expected output. `<name>.violations` lists the RFC 4287 violations of the output.
+//! Run the tests with `UPDATE_GOLDENS=1` to rewrite the goldens after an intended change of the
+//! output.
+
+use std::{
+    env,
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use futures::{future, stream};
+use hyper::Body;
+use reqwest::Url;
+
+use super::{jvcmusic_co_jp, kadokawa_co_jp, kemono_friends_sega_jp, Transcode};
+use crate::{feed::Feed, validate};
+
+/// Size of the chunks the fixtures are fed in, small enough to split tokens and characters.
+const CHUNK_SIZE: usize = 7;
+
+#[tokio::test]
+async fn kemono_friends_sega_jp() {
+    let url = "https://kemono-friends.sega.jp/news/articles.json";
+    golden(kemono_friends_sega_jp::Transcode, url, "articles").await;
+}
+
+#[tokio::test]
+async fn kadokawa_co_jp() {
+    let url = "https://www.kadokawa.co.jp/json.jsp?id=342&category=comic&sort=new";
+    golden(kadokawa_co_jp::Transcode, url, "search").await;
+}
+
+#[tokio::test]
+async fn jvcmusic_co_jp() {
+    let url = "https://www.jvcmusic.co.jp/-/News/A025287.json";
+    golden(jvcmusic_co_jp::Transcode, url, "news").await;
+}
+
+#[tokio::test]
+async fn drops_unknown_dates() {
+    let url = "https://kemono-friends.sega.jp/news/articles.json";
+    let input = r#"{"articles":[{"id":"1","title":"t","date":"近日","modified":"2021-09-17"}]}"#;
+    let input = stream::iter(vec![Ok(Bytes::from_static(input.as_bytes()))]);
+    let output = super::Source::KemonoFriendsSegaJp
+        .transcode_to_vec(url.parse().unwrap(), input)
+        .await
+        .unwrap();
+
+    let feed = Feed::parse(&output).unwrap();
+    assert_eq!(feed.entries[0].published, None);
+    assert_eq!(
+        feed.entries[0].updated.as_deref(),
+        Some("2021-09-17T00:00:00+09:00")
+    );
+}
+
+async fn golden<T>(transcoder: T, url: &str, name: &str)
+where
+    T: Transcode,
+    T::Error: Debug,
+{
+    let url: Url = url.parse().unwrap();
+    let source = super::Source::from_url(&url).unwrap();
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(source.name());
+
+    let input = fs::read(dir.join(format!("{}.json", name))).unwrap();
+    let chunks = input
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+        .collect::<Vec<_>>();
+
+    let (tx, body) = Body::channel();
+    let task = transcoder.transcode(url, stream::iter(chunks), tx);
+    let (result, output) = future::join(task, hyper::body::to_bytes(body)).await;
+    result.unwrap();
+    let output = output.unwrap();
+
+    // Rewriting the dates of the polled feeds must keep the rest of the output intact.
+    let feed = Feed::parse(&output).unwrap();
+    assert_eq!(feed.rewrite_updated(&output).unwrap(), output);
+
+    // Known violations of RFC 4287 are kept next to the golden so that new ones stand out.
+    let violations = validate::validate(&output)
+        .iter()
+        .map(|violation| format!("{}\n", violation))
+        .collect::<String>();
+
+    compare(&dir.join(format!("{}.atom", name)), &output);
+    compare(
+        &dir.join(format!("{}.violations", name)),
+        violations.as_bytes(),
+    );
+}
+
+fn compare(path: &Path, output: &[u8]) {
+    if env::var_os("UPDATE_GOLDENS").is_some() {
+        fs::write(path, output).unwrap();
+        return;
+    }
+    let expected = fs::read(path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read `{}` (run with `UPDATE_GOLDENS=1` to create it): {}",
+            path.display(),
+            e
+        )
+    });
+    assert_eq!(
+        String::from_utf8_lossy(output),
+        String::from_utf8_lossy(&expected),
+        "output differs from `{}`",
+        path.display(),
+    );
+}
@@ -0,0 +1,226 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hyper::Method;
+use reqwest::{Client, Request as Reqwest, Response as Reswponse};
+use tracing::{debug, warn};
+
+use crate::{
+    config, metrics,
+    status::Status,
+    tape::{self, Tape},
+    transcode::Source,
+    util,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// A client for a single upstream site, with retries and a circuit breaker.
+pub struct Upstream {
+    source: Source,
+    client: Client,
+    config: config::Source,
+    origins: config::Origins,
+    tape: Option<Arc<Tape>>,
+    status: Arc<Status>,
+    breaker: Breaker,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The circuit breaker is open. Contains the time until the next trial request.
+    CircuitOpen(Duration),
+    Request(reqwest::Error),
+    /// No response has been recorded for the request, or the recording is broken.
+    Replay(anyhow::Error),
+}
+
+struct Breaker {
+    threshold: u32,
+    open_duration: Duration,
+    state: Mutex<BreakerState>,
+}
+
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Upstream {
+    pub fn new(
+        source: Source,
+        config: &config::Source,
+        origins: &config::Origins,
+        user_agent: &str,
+        tape: Option<Arc<Tape>>,
+        status: Arc<Status>,
+    ) -> reqwest::Result<Self> {
+        let client = Client::builder()
+            .referer(false)
+            .gzip(true)
+            .brotli(true)
+            .user_agent(user_agent)
+            .default_headers(config.headers.clone())
+            .connect_timeout(config.connect_timeout)
+            .build()?;
+        Ok(Upstream {
+            source,
+            client,
+            config: config.clone(),
+            origins: origins.clone(),
+            tape,
+            status,
+            breaker: Breaker::new(config.failure_threshold, config.open_duration),
+        })
+    }
+
+    /// Executes `request`, or replays the recorded response for it.
+    pub async fn execute(&self, request: Reqwest) -> Result<Reswponse, Error> {
+        let url = request.url().clone();
+        let result = match self.tape.as_deref() {
+            Some(Tape::Replay(dir)) => tape::replay(dir, &url).await.map_err(Error::Replay),
+            Some(Tape::Record(dir)) => match self.fetch(request).await {
+                Ok(resw) => tape::record(dir, &url, resw).await.map_err(Error::Request),
+                Err(e) => Err(e),
+            },
+            None => self.fetch(request).await,
+        };
+        match result {
+            Ok(ref resw) => self.status.fetched(self.source, Ok(resw.status())),
+            // Nothing was requested.
+            Err(Error::CircuitOpen(_)) => {}
+            Err(ref e) => self.status.fetched(self.source, Err(e)),
+        }
+        result
+    }
+
+    /// Executes `request`, retrying idempotent requests that fail or get a 5xx response.
+    async fn fetch(&self, mut request: Reqwest) -> Result<Reswponse, Error> {
+        if let Err(retry_after) = self.breaker.acquire() {
+            metrics::upstream_error(self.source, "circuit-open");
+            return Err(Error::CircuitOpen(retry_after));
+        }
+
+        *request.url_mut() = self.origins.map(request.url());
+        *request.timeout_mut() = Some(self.config.timeout);
+        let idempotent = matches!(*request.method(), Method::GET | Method::HEAD);
+
+        let mut attempt = 0;
+        loop {
+            let retry = idempotent && attempt < self.config.retries;
+            let result = match request.try_clone() {
+                Some(req) if retry => self.send(req).await,
+                _ => return self.finish(self.send(request).await),
+            };
+            match result {
+                Ok(resw) if !resw.status().is_server_error() => return self.finish(Ok(resw)),
+                Ok(resw) => {
+                    let (url, status) = (resw.url(), resw.status().as_u16());
+                    warn!(%url, status, attempt, "upstream server error, retrying");
+                }
+                Err(e) => warn!(error = %e, attempt, "upstream request failed, retrying"),
+            }
+            tokio::time::delay_for(util::backoff(self.config.retry_backoff, attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Makes a single attempt of a request and records it in the metrics.
+    async fn send(&self, request: Reqwest) -> reqwest::Result<Reswponse> {
+        let url = request.url().clone();
+        let start = Instant::now();
+        let result = self.client.execute(request).await;
+        let elapsed = start.elapsed();
+        metrics::upstream_request(self.source, elapsed);
+        let elapsed_ms = elapsed.as_millis() as u64;
+        match result {
+            Ok(ref resw) => debug!(%url, status = resw.status().as_u16(), elapsed_ms, "upstream"),
+            Err(ref e) => debug!(%url, error = %e, elapsed_ms, "upstream"),
+        }
+        match result {
+            Ok(ref resw) if resw.status().is_server_error() => {
+                metrics::upstream_error(self.source, "server-error");
+            }
+            Ok(_) => {}
+            Err(ref e) if e.is_timeout() => metrics::upstream_error(self.source, "timeout"),
+            Err(_) => metrics::upstream_error(self.source, "request"),
+        }
+        result
+    }
+
+    fn finish(&self, result: reqwest::Result<Reswponse>) -> Result<Reswponse, Error> {
+        match result {
+            Ok(ref resw) if !resw.status().is_server_error() => self.breaker.succeed(),
+            _ => self.breaker.fail(),
+        }
+        result.map_err(Error::Request)
+    }
+}
+
+impl Breaker {
+    fn new(threshold: u32, open_duration: Duration) -> Self {
+        Breaker {
+            threshold,
+            open_duration,
+            state: Mutex::new(BreakerState {
+                failures: 0,
+                open_until: None,
+            }),
+        }
+    }
+
+    /// Checks whether a request may be made, letting a single trial through a half-open breaker.
+    fn acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            None => Ok(()),
+            Some(until) => {
+                let now = Instant::now();
+                if now < until {
+                    Err(until - now)
+                } else {
+                    state.open_until = Some(now + self.open_duration);
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    fn succeed(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures = 0;
+        state.open_until = None;
+    }
+
+    fn fail(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures = state.failures.saturating_add(1);
+        if state.failures >= self.threshold {
+            state.open_until = Some(Instant::now() + self.open_duration);
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::CircuitOpen(_) => write!(f, "circuit breaker is open"),
+            Error::Request(ref e) => e.fmt(f),
+            Error::Replay(ref e) => write!(f, "failed to replay the response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::CircuitOpen(_) => None,
+            Error::Request(ref e) => Some(e),
+            Error::Replay(ref e) => Some(e.as_ref()),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Tests of the retries and the circuit breaker against a scripted upstream.
+
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Response, Server, StatusCode,
+};
+use reqwest::{Request as Reqwest, Url};
+
+use super::{Error, Upstream};
+use crate::{config, status::Status, transcode::Source};
+
+/// An upstream answering with the scripted statuses after their delays, then with `200 OK`.
+struct Script {
+    addr: SocketAddr,
+    replies: Arc<Mutex<VecDeque<(StatusCode, Duration)>>>,
+    requests: Arc<AtomicUsize>,
+}
+
+impl Script {
+    fn start() -> Self {
+        let replies = Arc::new(Mutex::new(VecDeque::<(StatusCode, Duration)>::new()));
+        let requests = Arc::new(AtomicUsize::new(0));
+        let make_service = {
+            let replies = replies.clone();
+            let requests = requests.clone();
+            make_service_fn(move |_| {
+                let replies = replies.clone();
+                let requests = requests.clone();
+                let service = service_fn(move |_| {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    let reply = replies.lock().unwrap().pop_front();
+                    let (status, delay) = reply.unwrap_or((StatusCode::OK, Duration::from_secs(0)));
+                    async move {
+                        tokio::time::delay_for(delay).await;
+                        let res = Response::builder().status(status).body(Body::default());
+                        Ok::<_, Infallible>(res.unwrap())
+                    }
+                });
+                async { Ok::<_, Infallible>(service) }
+            })
+        };
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        Script {
+            addr,
+            replies,
+            requests,
+        }
+    }
+
+    fn push(&self, status: StatusCode, delay: Duration) {
+        self.replies.lock().unwrap().push_back((status, delay));
+    }
+
+    fn requests(&self) -> usize {
+        self.requests.swap(0, Ordering::SeqCst)
+    }
+
+    fn request(&self) -> Reqwest {
+        let url: Url = format!("http://{}/feed", self.addr).parse().unwrap();
+        Reqwest::new(Method::GET, url)
+    }
+}
+
+fn upstream(config: &str) -> Upstream {
+    let config: config::Source = toml::from_str(config).unwrap();
+    let origins = config::Origins::default();
+    let status = Arc::new(Status::default());
+    Upstream::new(Source::JvcmusicCoJp, &config, &origins, "", None, status).unwrap()
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let script = Script::start();
+    let upstream = upstream("retries = 2\nretry-backoff = 0");
+
+    script.push(StatusCode::INTERNAL_SERVER_ERROR, Duration::from_secs(0));
+    script.push(StatusCode::BAD_GATEWAY, Duration::from_secs(0));
+    let resw = upstream.execute(script.request()).await.unwrap();
+    assert_eq!(resw.status(), StatusCode::OK);
+    assert_eq!(script.requests(), 3);
+
+    // The last error is returned once the retries run out.
+    for _ in 0..3 {
+        script.push(StatusCode::SERVICE_UNAVAILABLE, Duration::from_secs(0));
+    }
+    let resw = upstream.execute(script.request()).await.unwrap();
+    assert_eq!(resw.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(script.requests(), 3);
+}
+
+#[tokio::test]
+async fn retries_timeouts() {
+    let script = Script::start();
+    let upstream = upstream("timeout = 0.1\nretries = 1\nretry-backoff = 0");
+
+    script.push(StatusCode::OK, Duration::from_secs(1));
+    let resw = upstream.execute(script.request()).await.unwrap();
+    assert_eq!(resw.status(), StatusCode::OK);
+    assert_eq!(script.requests(), 2);
+
+    script.push(StatusCode::OK, Duration::from_secs(1));
+    script.push(StatusCode::OK, Duration::from_secs(1));
+    match upstream.execute(script.request()).await {
+        Err(Error::Request(e)) => assert!(e.is_timeout(), "{}", e),
+        result => panic!("{:?}", result.map(|resw| resw.status())),
+    }
+    assert_eq!(script.requests(), 2);
+}
+
+#[tokio::test]
+async fn opens_after_consecutive_failures() {
+    let script = Script::start();
+    let upstream = upstream("retries = 0\nfailure-threshold = 2\nopen-duration = 60");
+
+    // A success resets the count.
+    script.push(StatusCode::INTERNAL_SERVER_ERROR, Duration::from_secs(0));
+    script.push(StatusCode::OK, Duration::from_secs(0));
+    script.push(StatusCode::INTERNAL_SERVER_ERROR, Duration::from_secs(0));
+    for _ in 0..3 {
+        upstream.execute(script.request()).await.unwrap();
+    }
+    assert_eq!(script.requests(), 3);
+
+    script.push(StatusCode::INTERNAL_SERVER_ERROR, Duration::from_secs(0));
+    upstream.execute(script.request()).await.unwrap();
+    match upstream.execute(script.request()).await {
+        Err(Error::CircuitOpen(retry_after)) => {
+            assert!(retry_after > Duration::from_secs(59), "{:?}", retry_after);
+            assert!(retry_after <= Duration::from_secs(60), "{:?}", retry_after);
+        }
+        result => panic!("{:?}", result.map(|resw| resw.status())),
+    }
+    assert_eq!(script.requests(), 1);
+}
+
+#[tokio::test]
+async fn closes_after_a_successful_trial() {
+    let script = Script::start();
+    let upstream = upstream("retries = 0\nfailure-threshold = 1\nopen-duration = 0.2");
+
+    script.push(StatusCode::INTERNAL_SERVER_ERROR, Duration::from_secs(0));
+    upstream.execute(script.request()).await.unwrap();
+    let result = upstream.execute(script.request()).await;
+    assert!(matches!(result, Err(Error::CircuitOpen(_))));
+
+    tokio::time::delay_for(Duration::from_millis(250)).await;
+    let resw = upstream.execute(script.request()).await.unwrap();
+    assert_eq!(resw.status(), StatusCode::OK);
+    let resw = upstream.execute(script.request()).await.unwrap();
+    assert_eq!(resw.status(), StatusCode::OK);
+    assert_eq!(script.requests(), 3);
+}
+
+#[tokio::test]
+async fn reopens_after_a_failed_trial() {
+    let script = Script::start();
+    let upstream = upstream("retries = 0\nfailure-threshold = 1\nopen-duration = 0.2");
+
+    script.push(StatusCode::INTERNAL_SERVER_ERROR, Duration::from_secs(0));
+    upstream.execute(script.request()).await.unwrap();
+
+    tokio::time::delay_for(Duration::from_millis(250)).await;
+    script.push(StatusCode::INTERNAL_SERVER_ERROR, Duration::from_secs(0));
+    let resw = upstream.execute(script.request()).await.unwrap();
+    assert_eq!(resw.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let result = upstream.execute(script.request()).await;
+    assert!(matches!(result, Err(Error::CircuitOpen(_))));
+    assert_eq!(script.requests(), 2);
+}
@@ -1,18 +1,32 @@
 use std::{
     error::Error,
+    fs,
     future::Future,
     io::{self, Read, Write},
     marker::Unpin,
     ops::Deref,
+    path::{Path, PathBuf},
     pin::Pin,
+    sync::{Mutex, MutexGuard},
     task::{Context, Poll},
+    time::Duration,
 };
 
 use bytes::Bytes;
 use futures::{Stream, StreamExt};
-use hyper::body::Sender;
-use serde::de;
-use xml::events::{BytesDecl, BytesEnd, BytesStart, Event};
+use hmac::{Hmac, Mac, NewMac};
+use hyper::{body::Sender, header::HeaderValue};
+use serde::{de, de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+
+use crate::{metrics, shutdown};
+
+#[cfg(test)]
+mod tests;
+
+/// Maximum length of the readable part of a `slug`.
+const SLUG_LEN: usize = 64;
 
 pub struct BodyWrite(Sender);
 
@@ -26,7 +40,7 @@ impl Write for BodyWrite {
     fn write(&mut self, b: &[u8]) -> io::Result<usize> {
         futures::executor::block_on(self.0.send_data(Bytes::copy_from_slice(b)))
             .map(|()| b.len())
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            .map_err(io::Error::other)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -64,7 +78,7 @@ where
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         while self.buf.len() <= self.pos {
             if let Some(b) = futures::executor::block_on(self.stream.next()) {
-                self.buf = b.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                self.buf = b.map_err(io::Error::other)?;
                 self.pos = 0;
             } else {
                 return Ok(0);
@@ -90,6 +104,32 @@ impl<T> Future for JoinHandle<T> {
     }
 }
 
+/// Runs `f` on the blocking thread pool. A shutdown waits for it to return.
+pub fn spawn_blocking<F, R>(f: F) -> tokio::task::JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let task = (metrics::blocking_task(), shutdown::task());
+    tokio::task::spawn_blocking(move || {
+        let _task = task;
+        f()
+    })
+}
+
+/// Spawns a background task that a shutdown waits for.
+pub fn spawn<F>(future: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let task = shutdown::task();
+    tokio::spawn(async move {
+        let _task = task;
+        future.await
+    })
+}
+
 pub fn tag<W, F, E>(writer: &mut xml::Writer<W>, start: BytesStart, body: F) -> Result<(), E>
 where
     W: Write,
@@ -110,15 +150,195 @@ where
     Ok(())
 }
 
-pub fn feed<W, F, E>(writer: &mut xml::Writer<W>, body: F) -> Result<(), E>
+/// Writes a feed by `author`. `body` writes the rest of the feed and raises the `Option` to the
+/// latest `<updated>` of its entries, which becomes the `<updated>` of the feed.
+pub fn feed<W, F, E>(writer: &mut xml::Writer<W>, author: &str, body: F) -> Result<(), E>
 where
     W: Write,
-    F: FnOnce(&mut xml::Writer<W>) -> Result<(), E>,
+    F: FnOnce(&mut xml::Writer<W>, &mut Option<String>) -> Result<(), E>,
     E: de::Error,
 {
     writer
-        .write_event(&Event::Decl(BytesDecl::new(b"1.0", Some(b"utf-8"), None)))
+        .write_event(Event::Decl(BytesDecl::new(b"1.0", Some(b"utf-8"), None)))
         .map_err(de::Error::custom)?;
     let start = BytesStart::borrowed(br#"feed xmlns="http://www.w3.org/2005/Atom""#, 4);
-    tag(writer, start, body)
+    tag(writer, start, |writer| {
+        let mut updated = None;
+        body(writer, &mut updated)?;
+        if let Some(updated) = updated {
+            text(writer, b"updated", &updated)?;
+        }
+        tag(writer, BytesStart::borrowed_name(b"author"), |writer| {
+            text(writer, b"name", author)
+        })
+    })
+}
+
+pub fn text<W, E>(writer: &mut xml::Writer<W>, name: &[u8], text: &str) -> Result<(), E>
+where
+    W: Write,
+    E: de::Error,
+{
+    write_text(writer, name, text).map_err(E::custom)
+}
+
+/// Writes the element `name` containing `text`.
+pub fn write_text<W: Write>(
+    writer: &mut xml::Writer<W>,
+    name: &[u8],
+    text: &str,
+) -> xml::Result<()> {
+    writer.write_event(Event::Start(BytesStart::borrowed_name(name)))?;
+    writer.write_event(Event::Text(BytesText::from_plain_str(text)))?;
+    writer.write_event(Event::End(BytesEnd::borrowed(name)))?;
+    Ok(())
+}
+
+/// Returns the unescaped value of the attribute `name` of `e`.
+pub fn attr(
+    reader: &xml::Reader<&[u8]>,
+    e: &BytesStart<'_>,
+    name: &[u8],
+) -> xml::Result<Option<String>> {
+    for a in e.attributes() {
+        let a = a?;
+        if a.key == name {
+            return Ok(Some(a.unescape_and_decode_value(reader)?));
+        }
+    }
+    Ok(None)
+}
+
+/// Normalizes the upstream `date`, dropping it with a warning if its format is unknown.
+pub fn normalize_date(date: &str) -> Option<String> {
+    match crate::date::normalize(date) {
+        Ok(date) => Some(date),
+        Err(e) => {
+            tracing::warn!(error = %e, "dropping a date in an unknown format");
+            None
+        }
+    }
+}
+
+/// Raises `latest` to the RFC 3339 date-time `date` in JST if it is later.
+pub fn raise(latest: &mut Option<String>, date: &str) {
+    if latest.as_deref().is_none_or(|latest| latest < date) {
+        *latest = Some(date.to_owned());
+    }
+}
+
+/// Writes `contents` to `path` through a temporary file.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", hex(&rand::random::<[u8; 4]>())));
+    let tmp = Path::new(&tmp);
+    let mut file = fs::File::create(tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(tmp, path)
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        write!(s, "{:02x}", b).unwrap();
+    }
+    s
+}
+
+/// Returns the `sha256=`-prefixed HMAC-SHA256 signature of `body` with the key `secret`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(body);
+    format!("sha256={}", hex(&mac.finalize().into_bytes()))
+}
+
+/// A value persisted as JSON in a file of the state directory, if one is configured.
+pub struct Persisted<T> {
+    path: Option<PathBuf>,
+    value: Mutex<T>,
+    /// Held from the serialization of the value until it is written, so that an older value
+    /// never overwrites a newer one.
+    write: tokio::sync::Mutex<()>,
+}
+
+impl<T> Persisted<T>
+where
+    T: Default + DeserializeOwned + Serialize,
+{
+    /// Reads the file `name` in `state_dir`, or starts with the default value.
+    pub fn load(state_dir: Option<&Path>, name: &str) -> anyhow::Result<Self> {
+        let path = state_dir.map(|dir| dir.join(name));
+        let value = match path {
+            Some(ref path) if path.exists() => json::from_slice(&fs::read(path)?)?,
+            _ => T::default(),
+        };
+        Ok(Persisted {
+            path,
+            value: Mutex::new(value),
+            write: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.value.lock().unwrap()
+    }
+
+    /// Writes the current value to the file.
+    pub async fn save(&self) -> anyhow::Result<()> {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return Ok(()),
+        };
+        let _write = self.write.lock().await;
+        let contents = json::to_vec(&*self.lock())?;
+        spawn_blocking(move || write_atomic(&path, &contents)).await??;
+        Ok(())
+    }
+}
+
+/// Returns `etag` as a weak entity tag.
+pub fn weak_etag(etag: &HeaderValue) -> HeaderValue {
+    if etag.as_bytes().starts_with(b"W/") {
+        return etag.clone();
+    }
+    let mut weak = b"W/".to_vec();
+    weak.extend_from_slice(etag.as_bytes());
+    HeaderValue::from_bytes(&weak).unwrap()
+}
+
+/// Escapes `s` for HTML text and quoted attribute values.
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Returns an exponential backoff delay with full jitter for the `attempt`-th retry.
+pub fn backoff(base: Duration, attempt: u32) -> Duration {
+    let max = base * 2u32.saturating_pow(attempt);
+    max.mul_f64(rand::random())
+}
+
+/// Returns a file name made of the alphanumeric characters of `url` after the scheme, shortened
+/// to `SLUG_LEN`, and a hash of the whole URL.
+pub fn slug(url: &url::Url) -> String {
+    let hash = Sha256::digest(url.as_str().as_bytes());
+    let url = &url[url::Position::BeforeHost..url::Position::AfterQuery];
+    let mut slug = String::with_capacity(url.len());
+    for c in url.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(SLUG_LEN);
+    slug.truncate(slug.trim_end_matches('-').len());
+    slug.push('-');
+    slug.push_str(&hex(&hash[..4]));
+    slug
 }
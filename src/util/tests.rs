@@ -0,0 +1,32 @@
+use super::{sign, slug, SLUG_LEN};
+
+#[test]
+fn slugs_are_unique_and_short() {
+    let a = slug(
+        &"https://www.kadokawa.co.jp/json.jsp?id=342&category=comic"
+            .parse()
+            .unwrap(),
+    );
+    let b = slug(
+        &"https://www.kadokawa.co.jp/json.jsp?id=342&category-comic"
+            .parse()
+            .unwrap(),
+    );
+    assert!(a.starts_with("www-kadokawa-co-jp-json-jsp-id-342-category-comic-"));
+    assert_ne!(a, b);
+
+    let long = format!(
+        "https://www.kadokawa.co.jp/json.jsp?id=342&q={}",
+        "a".repeat(256)
+    );
+    let long = slug(&long.parse().unwrap());
+    assert!(long.len() <= SLUG_LEN + 9);
+}
+
+#[test]
+fn signs_with_hmac_sha256() {
+    assert_eq!(
+        sign("key", b"The quick brown fox jumps over the lazy dog"),
+        "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+    );
+}
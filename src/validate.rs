@@ -0,0 +1,245 @@
+//! Checks of the produced feeds against the requirements of RFC 4287.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+use reqwest::Url;
+use xml::events::Event;
+
+use crate::{config::Validate, date, transcode::Source, util};
+
+#[cfg(test)]
+mod tests;
+
+const ATOM_NS: &[u8] = b"http://www.w3.org/2005/Atom";
+
+/// A requirement of RFC 4287 that a feed does not meet.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Violation {
+    /// Location of the offending element, e.g. `feed/entry[2]/updated`.
+    pub path: String,
+    pub message: String,
+}
+
+/// Children of an `atom:feed` or `atom:entry` element seen so far.
+#[derive(Default)]
+struct Element {
+    path: String,
+    counts: HashMap<Vec<u8>, usize>,
+    alternate: bool,
+}
+
+/// Checks the Atom document `atom` and returns every violation found.
+pub fn validate(atom: &[u8]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if let Err(e) = walk(atom, &mut violations) {
+        violations.push(Violation {
+            path: "feed".to_owned(),
+            message: format!("not well-formed: {}", e),
+        });
+    }
+    violations
+}
+
+/// Validates `atom` as configured for `source`, logging the violations.
+pub fn check(source: Source, mode: Validate, url: &Url, atom: &[u8]) -> anyhow::Result<()> {
+    if mode == Validate::Off {
+        return Ok(());
+    }
+    let violations = validate(atom);
+    for violation in &violations {
+        tracing::warn!(source = source.name(), %url, %violation, "invalid Atom");
+    }
+    if mode == Validate::Reject && !violations.is_empty() {
+        anyhow::bail!("the feed has {} violations of RFC 4287", violations.len());
+    }
+    Ok(())
+}
+
+fn walk(atom: &[u8], violations: &mut Vec<Violation>) -> xml::Result<()> {
+    let mut reader = xml::Reader::from_reader(atom);
+    let (mut buf, mut ns_buf) = (Vec::new(), Vec::new());
+
+    // Local names of the open elements, `None` for the ones outside the Atom namespace.
+    let mut stack: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut feed = Element {
+        path: "feed".to_owned(),
+        ..Element::default()
+    };
+    let mut entry: Option<Element> = None;
+    let (mut entries, mut entries_without_author) = (0, 0);
+    // Text of the `atom:id` or Date construct being read.
+    let mut text: Option<String> = None;
+
+    loop {
+        let (ns, event) = reader.read_namespaced_event(&mut buf, &mut ns_buf)?;
+        let atom_ns = ns == Some(ATOM_NS);
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let empty = matches!(event, Event::Empty(_));
+                let name = e.local_name().to_vec();
+                let in_feed = stack.len() == 1;
+                let parent = match *stack.as_slice() {
+                    [] => {
+                        if !atom_ns || name != b"feed" {
+                            violations.push(Violation {
+                                path: String::from_utf8_lossy(&name).into_owned(),
+                                message: "the root element is not `atom:feed`".to_owned(),
+                            });
+                            return Ok(());
+                        }
+                        None
+                    }
+                    [Some(_)] => Some(&mut feed),
+                    [Some(_), Some(ref e)] if e == b"entry" => entry.as_mut(),
+                    _ => None,
+                };
+
+                if let (Some(parent), true) = (parent, atom_ns) {
+                    let count = parent.counts.entry(name.clone()).or_insert(0);
+                    *count += 1;
+                    match &*name {
+                        b"link" => {
+                            let (href, rel) = (
+                                util::attr(&reader, e, b"href")?,
+                                util::attr(&reader, e, b"rel")?,
+                            );
+                            if href.is_none() {
+                                violations.push(Violation {
+                                    path: format!("{}/link", parent.path),
+                                    message: "missing the `href` attribute".to_owned(),
+                                });
+                            }
+                            if rel.as_deref().is_none_or(|rel| rel == "alternate") {
+                                parent.alternate = true;
+                            }
+                        }
+                        b"id" | b"updated" | b"published" => text = Some(String::new()),
+                        _ => {}
+                    }
+                }
+
+                if atom_ns && in_feed && name == b"entry" {
+                    entries += 1;
+                    entry = Some(Element {
+                        path: format!("feed/entry[{}]", entries),
+                        ..Element::default()
+                    });
+                }
+
+                if !empty {
+                    stack.push(if atom_ns { Some(name) } else { None });
+                } else if let Some(text) = text.take() {
+                    let parent = entry.as_ref().filter(|_| stack.len() > 1).unwrap_or(&feed);
+                    check_text(parent, &name, &text, violations);
+                }
+            }
+            Event::Text(ref e) => {
+                if let Some(ref mut text) = text {
+                    text.push_str(&e.unescape_and_decode(&reader)?);
+                }
+            }
+            Event::CData(ref e) => {
+                if let Some(ref mut text) = text {
+                    text.push_str(&reader.decode(e.escaped()));
+                }
+            }
+            Event::End(_) => {
+                let name = stack.pop().flatten();
+                match (stack.len(), name) {
+                    (0, _) => finish(&feed, false, violations),
+                    (1, Some(ref name)) if name == b"entry" => {
+                        if let Some(entry) = entry.take() {
+                            if !entry.counts.contains_key(&b"author"[..]) {
+                                entries_without_author += 1;
+                            }
+                            finish(&entry, true, violations);
+                        }
+                    }
+                    (_, Some(ref name)) => {
+                        if let Some(text) = text.take() {
+                            let parent =
+                                entry.as_ref().filter(|_| stack.len() > 1).unwrap_or(&feed);
+                            check_text(parent, name, &text, violations);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    // Entries inherit the authors of the feed.
+    if !feed.counts.contains_key(&b"author"[..]) && (entries == 0 || entries_without_author > 0) {
+        violations.push(Violation {
+            path: "feed".to_owned(),
+            message: "must contain `atom:author` unless every entry does".to_owned(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks the cardinality of the children of a finished `atom:feed` or `atom:entry`.
+fn finish(element: &Element, entry: bool, violations: &mut Vec<Violation>) {
+    let count = |name: &str| element.counts.get(name.as_bytes()).copied().unwrap_or(0);
+    let mut violation = |message: String| {
+        violations.push(Violation {
+            path: element.path.clone(),
+            message,
+        })
+    };
+
+    for &name in &["id", "title", "updated"] {
+        if count(name) != 1 {
+            violation(format!(
+                "must contain exactly one `atom:{}`, found {}",
+                name,
+                count(name)
+            ));
+        }
+    }
+    let at_most_one: &[&str] = if entry {
+        &["content", "published", "rights", "source", "summary"]
+    } else {
+        &["generator", "icon", "logo", "rights", "subtitle"]
+    };
+    for &name in at_most_one {
+        if count(name) > 1 {
+            violation(format!(
+                "may contain at most one `atom:{}`, found {}",
+                name,
+                count(name)
+            ));
+        }
+    }
+    if entry && count("content") == 0 && !element.alternate {
+        violation("must contain `atom:content` or an alternate `atom:link`".to_owned());
+    }
+}
+
+/// Checks the text of an `atom:id` or a Date construct.
+fn check_text(parent: &Element, name: &[u8], text: &str, violations: &mut Vec<Violation>) {
+    let name = String::from_utf8_lossy(name);
+    let text = text.trim();
+    let message = match &*name {
+        "id" if text.parse::<Url>().is_err() => "is not an absolute IRI",
+        "updated" | "published" if !date::is_rfc3339(text) => "is not an RFC 3339 date-time",
+        _ => return,
+    };
+    violations.push(Violation {
+        path: format!("{}/{}", parent.path, name),
+        message: format!("`{}` {}", text, message),
+    });
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
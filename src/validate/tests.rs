@@ -0,0 +1,100 @@
+//! Tests of the RFC 4287 checks.
+
+use super::validate;
+
+const VALID: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:ext="urn:example:ext">
+<title>Example</title>
+<link href="https://example.com/"/>
+<updated>2021-09-25T18:30:00+09:00</updated>
+<author><name>Example</name></author>
+<id>tag:example.com,2021:feed</id>
+<ext:id>not an IRI</ext:id>
+<entry>
+<id>tag:example.com,2021:1</id>
+<link href="https://example.com/1"/>
+<title>First</title>
+<published>2021-09-24T12:00:00.5+09:00</published>
+<updated>2021-09-25T18:30:00Z</updated>
+<ext:updated>yesterday</ext:updated>
+</entry>
+<entry>
+<id>tag:example.com,2021:2</id>
+<title>Second</title>
+<content type="text">Text</content>
+<updated>2021-02-28T00:00:00-05:00</updated>
+</entry>
+</feed>
+"#;
+
+fn messages(atom: &str) -> Vec<String> {
+    validate(atom.as_bytes())
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn accepts_valid_feed() {
+    assert_eq!(messages(VALID), Vec::<String>::new());
+}
+
+#[test]
+fn reports_missing_elements() {
+    let atom = VALID
+        .replace("<updated>2021-09-25T18:30:00+09:00</updated>\n", "")
+        .replace("<author><name>Example</name></author>\n", "")
+        .replace("<link href=\"https://example.com/1\"/>\n", "")
+        .replace(
+            "<title>Second</title>\n",
+            "<title>Second</title><title>Again</title>\n",
+        );
+    assert_eq!(
+        messages(&atom),
+        [
+            "feed/entry[1]: must contain `atom:content` or an alternate `atom:link`",
+            "feed/entry[2]: must contain exactly one `atom:title`, found 2",
+            "feed: must contain exactly one `atom:updated`, found 0",
+            "feed: must contain `atom:author` unless every entry does",
+        ],
+    );
+}
+
+#[test]
+fn accepts_entry_authors() {
+    let atom = VALID
+        .replace("<author><name>Example</name></author>\n", "")
+        .replace("</entry>", "<author><name>Writer</name></author></entry>");
+    assert_eq!(messages(&atom), Vec::<String>::new());
+}
+
+#[test]
+fn reports_invalid_text() {
+    let atom = VALID
+        .replace(
+            "<updated>2021-09-25T18:30:00Z</updated>",
+            "<updated>2021-09-25 18:30:00</updated>",
+        )
+        .replace("2021-02-28T", "2021-02-29T")
+        .replace("<id>tag:example.com,2021:feed</id>", "<id>feed</id>");
+    assert_eq!(
+        messages(&atom),
+        [
+            "feed/id: `feed` is not an absolute IRI",
+            "feed/entry[1]/updated: `2021-09-25 18:30:00` is not an RFC 3339 date-time",
+            "feed/entry[2]/updated: `2021-02-29T00:00:00-05:00` is not an RFC 3339 date-time",
+        ],
+    );
+}
+
+#[test]
+fn reports_other_documents() {
+    assert_eq!(
+        messages(r#"<rss version="2.0"><channel/></rss>"#),
+        ["rss: the root element is not `atom:feed`"],
+    );
+    assert_eq!(
+        messages("<feed xmlns=\"http://www.w3.org/2005/Atom\"><title></feed>"),
+        ["feed: not well-formed: Expecting </title> found </feed>"],
+    );
+}
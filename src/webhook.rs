@@ -0,0 +1,229 @@
+//! Notifications of new entries of the polled feeds to webhooks.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use hyper::{
+    header::{CONTENT_TYPE, RETRY_AFTER},
+    StatusCode,
+};
+use reqwest::{Client, Url};
+use tracing::{warn, Instrument};
+
+use crate::{
+    config::{Config, Webhook, WebhookFormat},
+    feed::{Entry, Feed},
+    router::State,
+    util::{self, Persisted},
+};
+
+#[cfg(test)]
+mod tests;
+
+const SIGNATURE: &str = "X-Kf-Feeder-Signature";
+
+pub struct Webhooks {
+    client: Client,
+    /// Ids of the entries seen so far in each feed.
+    seen: Persisted<BTreeMap<Url, BTreeSet<String>>>,
+}
+
+impl Webhooks {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let client = Client::builder()
+            .referer(false)
+            .user_agent(&*config.user_agent)
+            .build()?;
+
+        let seen = Persisted::load(config.state_dir.as_deref(), "seen.json")?;
+        Ok(Webhooks { client, seen })
+    }
+
+    pub async fn save(&self) -> anyhow::Result<()> {
+        self.seen.save().await
+    }
+}
+
+/// Sends the entries of `feed` that have not been seen before to the webhooks.
+pub async fn notify(state: &Arc<State>, url: &Url, feed: &Feed) -> anyhow::Result<()> {
+    if state.config.webhooks.is_empty() {
+        return Ok(());
+    }
+
+    let (entries, changed) = {
+        let mut seen = state.webhooks.seen.lock();
+        see(&mut seen, url, feed)
+    };
+
+    for (i, webhook) in state.config.webhooks.iter().enumerate() {
+        if entries.is_empty() || !webhook.notifies(url) {
+            continue;
+        }
+        let state = state.clone();
+        let url = url.clone();
+        let feed = feed.clone();
+        let entries = entries.clone();
+        let task = async move {
+            let webhook = &state.config.webhooks[i];
+            for entry in &entries {
+                let payload = payload(webhook.format, &url, &feed, entry);
+                if let Err(e) = deliver(&state.webhooks.client, webhook, payload).await {
+                    let webhook = redact(&webhook.url);
+                    warn!(%webhook, entry = %entry.id, error = %e, "webhook delivery failed");
+                }
+            }
+        };
+        util::spawn(task.in_current_span());
+    }
+
+    if changed {
+        state.webhooks.seen.save().await?;
+    }
+
+    Ok(())
+}
+
+/// Marks the entries of `feed` as seen, returning the new ones and whether `seen` has changed.
+fn see(seen: &mut BTreeMap<Url, BTreeSet<String>>, url: &Url, feed: &Feed) -> (Vec<Entry>, bool) {
+    let first = !seen.contains_key(url);
+    let seen = seen.entry(url.clone()).or_default();
+    let len = seen.len();
+    seen.retain(|id| feed.entries.iter().any(|entry| entry.id == *id));
+    let mut changed = seen.len() != len;
+
+    let mut entries = Vec::new();
+    // Feeds list the newest entries first.
+    for entry in feed.entries.iter().rev() {
+        if seen.insert(entry.id.clone()) {
+            changed = true;
+            if !first {
+                entries.push(entry.clone());
+            }
+        }
+    }
+    (entries, changed)
+}
+
+/// Posts `payload` to `webhook`, retrying on errors, 5xx and 429 responses.
+async fn deliver(client: &Client, webhook: &Webhook, payload: json::Value) -> anyhow::Result<()> {
+    let body = json::to_vec(&payload)?;
+    let signature = webhook
+        .secret
+        .as_ref()
+        .map(|secret| util::sign(secret, &body));
+
+    let mut attempt = 0;
+    loop {
+        let mut req = client
+            .post(webhook.url.clone())
+            .timeout(webhook.timeout)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some(ref signature) = signature {
+            req = req.header(SIGNATURE, signature);
+        }
+
+        let (error, retry_after) = match req.send().await {
+            Ok(res) if res.status().is_success() => return Ok(()),
+            Ok(res)
+                if res.status() == StatusCode::TOO_MANY_REQUESTS
+                    || res.status().is_server_error() =>
+            {
+                let retry_after = res
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok()?.parse::<f64>().ok())
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+                (
+                    anyhow::anyhow!("responded with {}", res.status()),
+                    retry_after,
+                )
+            }
+            Ok(res) => anyhow::bail!("responded with {}", res.status()),
+            Err(e) => {
+                let e = e.to_string();
+                let e = e.replace(webhook.url.as_str(), &redact(&webhook.url));
+                (anyhow::anyhow!(e), None)
+            }
+        };
+
+        if attempt >= webhook.retries {
+            return Err(error);
+        }
+        let url = redact(&webhook.url);
+        warn!(webhook = %url, error = %error, attempt, "webhook delivery failed, retrying");
+        let delay = retry_after.unwrap_or_else(|| util::backoff(webhook.retry_backoff, attempt));
+        let delay = delay.min(webhook.max_backoff);
+        tokio::time::delay_for(delay).await;
+        attempt += 1;
+    }
+}
+
+fn payload(format: WebhookFormat, url: &Url, feed: &Feed, entry: &Entry) -> json::Value {
+    match format {
+        WebhookFormat::Json => json::json!({
+            "feed": {
+                "id": feed.id,
+                "title": feed.title,
+                "link": feed.link,
+                "url": url,
+            },
+            "entry": {
+                "id": entry.id,
+                "title": entry.title,
+                "link": entry.link,
+                "published": entry.published,
+                "updated": entry.updated,
+                "content": entry.content,
+                "authors": entry.authors,
+                "categories": entry.categories,
+            },
+        }),
+        // Limits from <https://discord.com/developers/docs/resources/channel#embed-object-embed-limits>.
+        WebhookFormat::Discord => json::json!({
+            "embeds": [{
+                "title": truncate(&entry.title, 256),
+                "url": entry.link,
+                "description": entry.content.as_ref().map(|content| truncate(content, 4096)),
+                "timestamp": entry.published,
+                "footer": { "text": truncate(&feed.title, 2048) },
+            }],
+        }),
+        WebhookFormat::Slack => {
+            let title = slack_escape(&entry.title);
+            let mut text = match entry.link {
+                Some(ref link) => format!("*<{}|{}>*", slack_escape(link), title),
+                None => format!("*{}*", title),
+            };
+            if let Some(ref content) = entry.content {
+                text.push('\n');
+                text.push_str(&slack_escape(content));
+            }
+            json::json!({ "text": text })
+        }
+    }
+}
+
+fn truncate(s: &str, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars) {
+        Some((i, _)) => {
+            let i = s[..i].char_indices().next_back().map_or(0, |(i, _)| i);
+            format!("{}…", &s[..i])
+        }
+        None => s.into(),
+    }
+}
+
+fn slack_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Returns the origin of a webhook URL for logging, as the rest of it often contains a token.
+fn redact(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
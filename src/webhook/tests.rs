@@ -0,0 +1,153 @@
+//! Tests of the change detection, payloads and delivery of the webhooks.
+
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use hyper::{
+    header::{CONTENT_TYPE, RETRY_AFTER},
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use reqwest::{Client, Url};
+
+use super::{deliver, payload, see, SIGNATURE};
+use crate::{
+    config::{Webhook, WebhookFormat},
+    feed::{Entry, Feed},
+    util,
+};
+
+fn feed(ids: &[&str]) -> Feed {
+    Feed {
+        id: "tag:example.com,2021:feed".to_owned(),
+        title: "Example".to_owned(),
+        link: Some("https://example.com/".to_owned()),
+        entries: ids
+            .iter()
+            .map(|&id| Entry {
+                id: id.to_owned(),
+                title: format!("Entry <{}> & more", id),
+                link: Some(format!("https://example.com/{}", id)),
+                published: Some("2021-09-25T18:30:00+09:00".to_owned()),
+                content: Some("x".repeat(5000)),
+                ..Entry::default()
+            })
+            .collect(),
+        ..Feed::default()
+    }
+}
+
+fn ids(entries: &[Entry]) -> Vec<&str> {
+    entries.iter().map(|entry| &*entry.id).collect()
+}
+
+#[test]
+fn detects_new_entries() {
+    let url = Url::parse("https://example.com/feed.json").unwrap();
+    let mut seen = BTreeMap::new();
+
+    let (entries, changed) = see(&mut seen, &url, &feed(&["2", "1"]));
+    assert_eq!((ids(&entries), changed), (vec![], true));
+
+    let (entries, changed) = see(&mut seen, &url, &feed(&["4", "3", "2", "1"]));
+    assert_eq!((ids(&entries), changed), (vec!["3", "4"], true));
+
+    let (entries, changed) = see(&mut seen, &url, &feed(&["4", "3", "2", "1"]));
+    assert_eq!((ids(&entries), changed), (vec![], false));
+
+    // Entries that leave the feed are forgotten.
+    let (entries, changed) = see(&mut seen, &url, &feed(&["4", "3"]));
+    assert_eq!((ids(&entries), changed), (vec![], true));
+    assert_eq!(seen[&url].iter().collect::<Vec<_>>(), ["3", "4"]);
+}
+
+#[test]
+fn formats_payloads() {
+    let url = Url::parse("https://example.com/feed.json").unwrap();
+    let feed = feed(&["1"]);
+    let entry = &feed.entries[0];
+
+    let json = payload(WebhookFormat::Json, &url, &feed, entry);
+    assert_eq!(json["feed"]["url"], "https://example.com/feed.json");
+    assert_eq!(json["entry"]["id"], "1");
+    assert_eq!(json["entry"]["title"], "Entry <1> & more");
+
+    let discord = payload(WebhookFormat::Discord, &url, &feed, entry);
+    let embed = &discord["embeds"][0];
+    assert_eq!(embed["title"], "Entry <1> & more");
+    assert_eq!(embed["url"], "https://example.com/1");
+    let description = embed["description"].as_str().unwrap();
+    assert_eq!(description.chars().count(), 4096);
+    assert!(description.ends_with('…'));
+    assert_eq!(embed["footer"]["text"], "Example");
+
+    let mut entry = entry.clone();
+    entry.content = Some("a < b".to_owned());
+    let slack = payload(WebhookFormat::Slack, &url, &feed, &entry);
+    assert_eq!(
+        slack["text"],
+        "*<https://example.com/1|Entry &lt;1&gt; &amp; more>*\na &lt; b"
+    );
+}
+
+#[tokio::test]
+async fn delivers_signed_payloads_with_retries() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let make_service = {
+        let requests = requests.clone();
+        make_service_fn(move |_| {
+            let requests = requests.clone();
+            let service = service_fn(move |request: Request<Body>| {
+                let requests = requests.clone();
+                async move {
+                    let (parts, body) = request.into_parts();
+                    let body = hyper::body::to_bytes(body).await.unwrap();
+                    let mut requests = requests.lock().unwrap();
+                    requests.push(Request::from_parts(parts, body));
+                    // The first attempt fails with a delay beyond the maximum backoff.
+                    let res = if requests.len() == 1 {
+                        Response::builder()
+                            .status(StatusCode::SERVICE_UNAVAILABLE)
+                            .header(RETRY_AFTER, "1e300")
+                    } else {
+                        Response::builder().status(StatusCode::NO_CONTENT)
+                    };
+                    let res = res.body(Body::default());
+                    Ok::<_, Infallible>(res.unwrap())
+                }
+            });
+            async { Ok::<_, Infallible>(service) }
+        })
+    };
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+
+    let webhook: Webhook = toml::from_str(&format!(
+        r#"
+        url = "http://{}/hook"
+        secret = "key"
+        retry-backoff = 0
+        max-backoff = 0
+        "#,
+        addr,
+    ))
+    .unwrap();
+    let payload = json::json!({ "text": "hello" });
+    deliver(&Client::new(), &webhook, payload.clone())
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    for request in &*requests {
+        assert_eq!(request.uri(), "/hook");
+        assert_eq!(request.headers()[CONTENT_TYPE], "application/json");
+        let body = request.body();
+        assert_eq!(json::from_slice::<json::Value>(body).unwrap(), payload);
+        assert_eq!(request.headers()[SIGNATURE], util::sign("key", body));
+    }
+}
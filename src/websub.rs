@@ -0,0 +1,337 @@
+//! A WebSub hub for the feeds polled in the background.
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use futures::StreamExt;
+use hyper::{
+    header::{HeaderValue, CONTENT_TYPE, LINK},
+    Body, Method, Request, Response, StatusCode,
+};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use tracing::{warn, Instrument};
+
+use crate::{
+    config::Config,
+    router::State,
+    util::{self, Persisted},
+};
+
+#[cfg(test)]
+mod tests;
+
+pub const PATH: &str = "/websub";
+
+/// Maximum size of a subscription request body.
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+pub struct Hub {
+    client: Client,
+    subscriptions: Persisted<Vec<Subscription>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Subscription {
+    /// The upstream URL of the feed.
+    topic: Url,
+    callback: Url,
+    secret: Option<String>,
+    /// Expiry time in seconds since the Unix epoch.
+    expires: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Mode {
+    Subscribe,
+    Unsubscribe,
+}
+
+impl Hub {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let client = Client::builder()
+            .referer(false)
+            .user_agent(&*config.user_agent)
+            .timeout(config.websub.timeout)
+            .build()?;
+
+        let subscriptions = Persisted::load(config.state_dir.as_deref(), "websub.json")?;
+        Ok(Hub {
+            client,
+            subscriptions,
+        })
+    }
+
+    pub async fn save(&self) -> anyhow::Result<()> {
+        self.subscriptions.save().await
+    }
+}
+
+impl Subscription {
+    /// Returns whether `self` and `other` are of the same callback to the same topic.
+    fn is_same(&self, other: &Subscription) -> bool {
+        self.topic == other.topic && self.callback == other.callback
+    }
+}
+
+/// Returns the `Link` header advertising the hub for the feed of the upstream `url`.
+pub fn link(config: &Config, url: &Url) -> Option<HeaderValue> {
+    if !config.websub.enabled || config.feed(url).is_none() {
+        return None;
+    }
+    let public_url = config.public_url.as_ref()?;
+    let link = format!(
+        r#"<{}>; rel="hub", <{}>; rel="self""#,
+        public_url.join(&PATH[1..]).ok()?,
+        topic(public_url, url),
+    );
+    HeaderValue::from_str(&link).ok()
+}
+
+/// Handles a subscription request.
+pub async fn handle(request: Request<Body>, state: Arc<State>) -> anyhow::Result<Response<Body>> {
+    let bad_request = |msg: &'static str| {
+        Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from(msg))
+            .unwrap())
+    };
+
+    if request.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header("Allow", "POST")
+            .body(Body::default())?);
+    }
+
+    let mut body = request.into_body();
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.next().await {
+        buf.extend_from_slice(&chunk?);
+        if buf.len() > MAX_REQUEST_SIZE {
+            return Ok(Response::builder()
+                .status(StatusCode::PAYLOAD_TOO_LARGE)
+                .body(Body::default())?);
+        }
+    }
+
+    let (mut mode, mut topic, mut callback, mut lease, mut secret) = (None, None, None, None, None);
+    for (key, value) in url::form_urlencoded::parse(&buf) {
+        match &*key {
+            "hub.mode" => mode = Some(value),
+            "hub.topic" => topic = Some(value),
+            "hub.callback" => callback = Some(value),
+            "hub.lease_seconds" => lease = Some(value),
+            "hub.secret" => secret = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    let mode = match mode.as_deref() {
+        Some("subscribe") => Mode::Subscribe,
+        Some("unsubscribe") => Mode::Unsubscribe,
+        _ => return bad_request("unrecognized `hub.mode`"),
+    };
+    let public_url = state.config.public_url.as_ref().unwrap();
+    let topic = match topic
+        .as_deref()
+        .and_then(|topic| topic.strip_prefix(public_url.as_str()))
+        .and_then(|url| url.parse::<Url>().ok())
+    {
+        Some(ref url) if state.config.feed(url).is_some() => url.clone(),
+        _ => return bad_request("`hub.topic` is not a feed served by this hub"),
+    };
+    let callback = match callback.and_then(|callback| callback.parse::<Url>().ok()) {
+        Some(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+        _ => return bad_request("invalid `hub.callback`"),
+    };
+    let lease = match lease {
+        None => state.config.websub.default_lease,
+        Some(lease) => match lease.parse() {
+            Ok(secs) => Duration::from_secs(secs).min(state.config.websub.max_lease),
+            Err(_) => return bad_request("invalid `hub.lease_seconds`"),
+        },
+    };
+    if secret.as_ref().is_some_and(|secret| secret.len() >= 200) {
+        return bad_request("`hub.secret` is too long");
+    }
+
+    let subscription = Subscription {
+        topic,
+        callback,
+        secret,
+        expires: (SystemTime::now() + lease)
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    };
+    if mode == Mode::Subscribe && !has_room(&state, &subscription) {
+        return Ok(Response::builder()
+            .status(StatusCode::TOO_MANY_REQUESTS)
+            .body(Body::from("too many subscriptions"))?);
+    }
+    let task = async move {
+        let callback = subscription.callback.clone();
+        if let Err(e) = verify(&state, mode, subscription, lease).await {
+            warn!(%callback, error = %e, "websub verification failed");
+        }
+    };
+    util::spawn(task.in_current_span());
+
+    Ok(Response::builder()
+        .status(StatusCode::ACCEPTED)
+        .body(Body::default())?)
+}
+
+/// Verifies the intent of the subscriber and applies the (un)subscription.
+async fn verify(
+    state: &Arc<State>,
+    mode: Mode,
+    subscription: Subscription,
+    lease: Duration,
+) -> anyhow::Result<()> {
+    let public_url = state.config.public_url.as_ref().unwrap();
+    let challenge = util::hex(&rand::random::<[u8; 16]>());
+
+    let mut url = subscription.callback.clone();
+    {
+        let mut query = url.query_pairs_mut();
+        let topic = topic(public_url, &subscription.topic);
+        match mode {
+            Mode::Subscribe => {
+                query.append_pair("hub.mode", "subscribe");
+                query.append_pair("hub.lease_seconds", &lease.as_secs().to_string());
+            }
+            Mode::Unsubscribe => {
+                query.append_pair("hub.mode", "unsubscribe");
+            }
+        }
+        query.append_pair("hub.topic", &topic);
+        query.append_pair("hub.challenge", &challenge);
+    }
+
+    let res = state.hub.client.get(url).send().await?;
+    let status = res.status();
+    if !status.is_success() {
+        anyhow::bail!("callback responded with {}", status);
+    }
+    // Only as much of the body as the challenge is read.
+    let mut body = res.bytes_stream();
+    let mut echo = Vec::new();
+    while let Some(chunk) = body.next().await {
+        echo.extend_from_slice(&chunk?);
+        if echo.len() > challenge.len() {
+            break;
+        }
+    }
+    if echo != challenge.as_bytes() {
+        anyhow::bail!("callback did not echo the challenge");
+    }
+
+    if mode == Mode::Subscribe && !has_room(state, &subscription) {
+        anyhow::bail!("too many subscriptions");
+    }
+    {
+        let mut subscriptions = state.hub.subscriptions.lock();
+        subscriptions.retain(|s| !s.is_same(&subscription));
+        if mode == Mode::Subscribe {
+            subscriptions.push(subscription);
+        }
+    }
+    state.hub.subscriptions.save().await
+}
+
+/// Returns whether `subscription` fits in the limits, replacing any previous one of its callback
+/// to its topic.
+fn has_room(state: &State, subscription: &Subscription) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let subscriptions = state.hub.subscriptions.lock();
+    let others = subscriptions
+        .iter()
+        .filter(|s| s.expires > now && !s.is_same(subscription));
+    let (total, per_topic) = others.fold((0, 0), |(total, per_topic), s| {
+        (
+            total + 1,
+            per_topic + usize::from(s.topic == subscription.topic),
+        )
+    });
+    let config = &state.config.websub;
+    total < config.max_subscriptions && per_topic < config.max_subscriptions_per_topic
+}
+
+/// Pushes the new content of the feed of the upstream `url` to its subscribers.
+pub fn publish(state: &Arc<State>, url: &Url, body: Bytes) {
+    if !state.config.websub.enabled {
+        return;
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let subscriptions = {
+        let mut subscriptions = state.hub.subscriptions.lock();
+        subscriptions.retain(|s| s.expires > now);
+        subscriptions
+            .iter()
+            .filter(|s| s.topic == *url)
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    for subscription in subscriptions {
+        let state = state.clone();
+        let body = body.clone();
+        let task = async move {
+            if let Err(e) = deliver(&state, &subscription, body).await {
+                let callback = &subscription.callback;
+                warn!(%callback, error = %e, "websub delivery failed");
+            }
+        };
+        util::spawn(task.in_current_span());
+    }
+}
+
+async fn deliver(
+    state: &Arc<State>,
+    subscription: &Subscription,
+    body: Bytes,
+) -> anyhow::Result<()> {
+    let mut req = state
+        .hub
+        .client
+        .post(subscription.callback.clone())
+        .header(CONTENT_TYPE, "application/atom+xml;charset=UTF-8");
+    if let Some(link) = link(&state.config, &subscription.topic) {
+        req = req.header(LINK, link);
+    }
+    if let Some(ref secret) = subscription.secret {
+        req = req.header("X-Hub-Signature", util::sign(secret, &body));
+    }
+
+    let res = req.body(body).send().await?;
+    match res.status() {
+        s if s.is_success() => Ok(()),
+        StatusCode::GONE => {
+            // The subscriber has asked to be removed.
+            state
+                .hub
+                .subscriptions
+                .lock()
+                .retain(|s| !s.is_same(subscription));
+            state.hub.subscriptions.save().await
+        }
+        s => anyhow::bail!("callback responded with {}", s),
+    }
+}
+
+/// Returns the URL under which the feeder serves the feed of the upstream `url`.
+fn topic(public_url: &Url, url: &Url) -> String {
+    format!("{}{}", public_url, url)
+}
@@ -0,0 +1,291 @@
+//! Tests of the verification, limits and delivery of the WebSub hub.
+
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use reqwest::Url;
+
+use super::{deliver, handle, publish, verify, Mode, Subscription};
+use crate::{config::Config, router::State};
+
+const TOPIC: &str = "https://kemono-friends.sega.jp/news/articles.json";
+
+/// A subscriber recording the requests to its callbacks.
+///
+/// It echoes the challenge except on `/reject` and responds to the deliveries on `/gone` with
+/// `410 Gone`.
+struct Subscriber {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Request<Bytes>>>>,
+}
+
+impl Subscriber {
+    fn start() -> Self {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let make_service = {
+            let requests = requests.clone();
+            make_service_fn(move |_| {
+                let requests = requests.clone();
+                let service = service_fn(move |request: Request<Body>| {
+                    let requests = requests.clone();
+                    async move {
+                        let (parts, body) = request.into_parts();
+                        let body = hyper::body::to_bytes(body).await.unwrap();
+                        let request = Request::from_parts(parts, body);
+                        let res = if request.method() == Method::GET {
+                            let challenge = request
+                                .uri()
+                                .query()
+                                .into_iter()
+                                .flat_map(|query| url::form_urlencoded::parse(query.as_bytes()))
+                                .find(|(key, _)| key == "hub.challenge")
+                                .map(|(_, value)| value.into_owned())
+                                .unwrap_or_default();
+                            if request.uri().path() == "/reject" {
+                                Response::new(Body::from("nope"))
+                            } else {
+                                Response::new(Body::from(challenge))
+                            }
+                        } else if request.uri().path() == "/gone" {
+                            let res = Response::builder().status(StatusCode::GONE);
+                            res.body(Body::default()).unwrap()
+                        } else {
+                            let res = Response::builder().status(StatusCode::NO_CONTENT);
+                            res.body(Body::default()).unwrap()
+                        };
+                        requests.lock().unwrap().push(request);
+                        Ok::<_, Infallible>(res)
+                    }
+                });
+                async { Ok::<_, Infallible>(service) }
+            })
+        };
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        Subscriber { addr, requests }
+    }
+
+    fn callback(&self, path: &str) -> Url {
+        format!("http://{}{}", self.addr, path).parse().unwrap()
+    }
+
+    fn take_requests(&self) -> Vec<Request<Bytes>> {
+        std::mem::take(&mut *self.requests.lock().unwrap())
+    }
+}
+
+fn state(max_subscriptions_per_topic: usize) -> Arc<State> {
+    let config = format!(
+        r#"
+        public-url = "https://feeds.example.com/"
+
+        [websub]
+        enabled = true
+        max-subscriptions-per-topic = {}
+
+        [[feeds]]
+        url = "{}"
+        "#,
+        max_subscriptions_per_topic, TOPIC,
+    );
+    let config: Config = toml::from_str(&config).unwrap();
+    Arc::new(State::new(config, None).unwrap())
+}
+
+fn subscription(callback: Url, secret: Option<&str>, expires: u64) -> Subscription {
+    Subscription {
+        topic: TOPIC.parse().unwrap(),
+        callback,
+        secret: secret.map(str::to_owned),
+        expires,
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn callbacks(state: &State) -> Vec<String> {
+    let subscriptions = state.hub.subscriptions.lock();
+    subscriptions
+        .iter()
+        .map(|s| s.callback.to_string())
+        .collect()
+}
+
+fn form(mode: &str, callback: &Url) -> Request<Body> {
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("hub.mode", mode)
+        .append_pair("hub.topic", &format!("https://feeds.example.com/{}", TOPIC))
+        .append_pair("hub.callback", callback.as_str())
+        .append_pair("hub.lease_seconds", "60")
+        .finish();
+    Request::post(super::PATH).body(Body::from(body)).unwrap()
+}
+
+/// Waits until the subscriptions have the `expected` callbacks.
+async fn wait_for(state: &State, expected: &[&str]) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while callbacks(state) != expected {
+        assert!(Instant::now() < deadline, "{:?}", callbacks(state));
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn verifies_subscriptions() {
+    let subscriber = Subscriber::start();
+    let state = state(100);
+    let callback = subscriber.callback("/callback?id=1");
+
+    let res = handle(form("subscribe", &callback), state.clone())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    wait_for(&state, &[callback.as_str()]).await;
+
+    let requests = subscriber.take_requests();
+    assert_eq!(requests.len(), 1);
+    let query = url::form_urlencoded::parse(requests[0].uri().query().unwrap().as_bytes())
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        query[..4],
+        [
+            "id=1".to_owned(),
+            "hub.mode=subscribe".to_owned(),
+            "hub.lease_seconds=60".to_owned(),
+            format!("hub.topic=https://feeds.example.com/{}", TOPIC),
+        ]
+    );
+    assert!(query[4].starts_with("hub.challenge="));
+
+    let res = handle(form("unsubscribe", &callback), state.clone())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    wait_for(&state, &[]).await;
+
+    let requests = subscriber.take_requests();
+    assert_eq!(requests.len(), 1);
+    let query = requests[0].uri().query().unwrap();
+    assert!(query.contains("hub.mode=unsubscribe"));
+}
+
+#[tokio::test]
+async fn rejects_wrong_challenges() {
+    let subscriber = Subscriber::start();
+    let state = state(100);
+    let subscription = subscription(subscriber.callback("/reject"), None, now() + 60);
+
+    let lease = Duration::from_secs(60);
+    let e = verify(&state, Mode::Subscribe, subscription, lease)
+        .await
+        .unwrap_err();
+    assert_eq!(e.to_string(), "callback did not echo the challenge");
+    assert_eq!(subscriber.take_requests().len(), 1);
+    assert!(callbacks(&state).is_empty());
+}
+
+#[tokio::test]
+async fn limits_subscriptions_per_topic() {
+    let subscriber = Subscriber::start();
+    let state = state(1);
+    let first = subscriber.callback("/first");
+    let second = subscriber.callback("/second");
+
+    // Expired subscriptions do not count.
+    let expired = subscription(second.clone(), None, now() - 1);
+    state.hub.subscriptions.lock().push(expired);
+    let res = handle(form("subscribe", &first), state.clone())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    wait_for(&state, &[second.as_str(), first.as_str()]).await;
+
+    let res = handle(form("subscribe", &second), state.clone())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Renewing a subscription does not count against the limit.
+    let res = handle(form("subscribe", &first), state.clone())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+}
+
+#[tokio::test]
+async fn delivers_signed_content() {
+    let subscriber = Subscriber::start();
+    let state = state(100);
+    let subscription = subscription(subscriber.callback("/callback"), Some("key"), now() + 60);
+
+    let body = Bytes::from_static(b"The quick brown fox jumps over the lazy dog");
+    deliver(&state, &subscription, body.clone()).await.unwrap();
+
+    let requests = subscriber.take_requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method(), Method::POST);
+    assert_eq!(request.body(), &body);
+    assert_eq!(
+        request.headers()["Content-Type"],
+        "application/atom+xml;charset=UTF-8",
+    );
+    assert_eq!(
+        request.headers()["X-Hub-Signature"],
+        "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8",
+    );
+}
+
+#[tokio::test]
+async fn removes_gone_subscribers() {
+    let subscriber = Subscriber::start();
+    let state = state(100);
+    let gone = subscription(subscriber.callback("/gone"), None, now() + 60);
+    let other = subscription(subscriber.callback("/callback"), None, now() + 60);
+    state
+        .hub
+        .subscriptions
+        .lock()
+        .extend(vec![gone.clone(), other]);
+
+    deliver(&state, &gone, Bytes::new()).await.unwrap();
+    wait_for(&state, &[subscriber.callback("/callback").as_str()]).await;
+}
+
+#[tokio::test]
+async fn drops_expired_subscriptions() {
+    let subscriber = Subscriber::start();
+    let state = state(100);
+    let expired = subscription(subscriber.callback("/expired"), None, now() - 1);
+    let live = subscription(subscriber.callback("/live"), None, now() + 60);
+    state.hub.subscriptions.lock().extend(vec![expired, live]);
+
+    publish(&state, &TOPIC.parse().unwrap(), Bytes::from_static(b"feed"));
+    let live = subscriber.callback("/live");
+    assert_eq!(callbacks(&state), [live.as_str()]);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while subscriber.requests.lock().unwrap().is_empty() {
+        assert!(Instant::now() < deadline, "the content was not delivered");
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+    }
+    let requests = subscriber.take_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].uri().path(), "/live");
+}